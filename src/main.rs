@@ -1,33 +1,81 @@
 pub mod state {
+    #[allow(clippy::module_inception)]
     pub mod state;
+    pub mod sessions;
+    pub mod cursors;
+    pub mod api_keys;
+    pub mod jwt;
 }
 
 pub mod middleware {
+    pub mod admin;
+    pub mod auth;
     pub mod mongo;
     pub mod headers;
+    pub mod session;
 }
 
 pub mod routes {
+    pub mod admin;
+    pub mod files;
     pub mod mongo;
+    pub mod ws;
 }
 
 pub mod utils {
+    pub mod bson;
+    pub mod extract;
     pub mod mongo;
+    pub mod pagination;
+    pub mod pipeline;
+    pub mod query_policy;
+    pub mod session;
+    pub mod token;
 }
 
 pub mod types {
+    pub mod errors;
+    pub mod principal;
     pub mod mongo {
+        pub mod operation;
+        pub mod read_preference;
         pub mod traits {
             pub mod requests;
         }
         pub mod requests {
             pub mod aggregate;
+            pub mod bulk_write;
+            pub mod count_documents;
+            pub mod create_collection;
+            pub mod create_index;
+            pub mod create_indexes;
             pub mod delete;
+            pub mod distinct;
+            pub mod drop_collection;
+            pub mod drop_index;
+            pub mod estimated_document_count;
+            pub mod files;
             pub mod find_one;
+            pub mod find_one_and_delete;
+            pub mod find_one_and_replace;
+            pub mod find_one_and_update;
             pub mod find;
+            pub mod get_more;
             pub mod insert_one;
             pub mod insert_many;
+            pub mod kill_cursors;
+            pub mod list_collections;
+            pub mod list_databases;
+            pub mod list_indexes;
+            pub mod rename_collection;
+            pub mod start_session;
+            pub mod subscription;
+            pub mod transaction;
             pub mod update;
+            pub mod watch;
+        }
+        pub mod responses {
+            pub mod bulk_write;
         }
     }
 }
@@ -37,15 +85,19 @@ use std::net::SocketAddr;
 use axum::Router;
 use axum::routing::get;
 use dotenv::dotenv;
-use routes::mongo::mongo_router;
+use routes::{admin::admin_router, mongo::mongo_router};
+use state::state::Mongo;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
+    let state = Mongo::new().await;
+
     let app = Router::new()
         .route("/", get(root))
-        .nest("/v1", mongo_router().await);
+        .nest("/v1", mongo_router(state.clone()))
+        .nest("/admin/v1", admin_router(state));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], env::var("PORT")
         .expect("Error: Failed to get PORT from environment")
@@ -54,7 +106,7 @@ async fn main() {
     
     println!("🚀 Server starting on {}", addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
@@ -0,0 +1,30 @@
+use axum::{middleware::Next, response::Response, extract::State};
+use hyper::Request;
+
+use crate::types::errors::ApiError;
+
+const ADMIN_KEY_HEADER: &str = "adminKey";
+
+/// Shared secret for the admin routes, read from `ADMIN_KEY`. Admin routes are disabled when unset.
+#[derive(Debug, Clone)]
+pub struct AdminKey(pub Option<String>);
+
+pub async fn admin_mw<B>(State(admin_key): State<AdminKey>, req: Request<B>, next: Next<B>) -> Result<Response, ApiError> {
+    let expected = match &admin_key.0 {
+        Some(key) => key,
+        None => {
+            return Err(ApiError::Forbidden("Admin routes are disabled".to_string()));
+        }
+    };
+
+    match req.headers().get(ADMIN_KEY_HEADER).map(|val| val.as_bytes()) {
+        Some(provided) if constant_time_eq(provided, expected.as_bytes()) => Ok(next.run(req).await),
+        Some(_) => Err(ApiError::Unauthorized("Invalid admin key".to_string())),
+        None => Err(ApiError::Unauthorized(format!("Missing {} header", ADMIN_KEY_HEADER)))
+    }
+}
+
+// Compares without short-circuiting so the key cannot be guessed byte by byte from response times
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use axum::{middleware::Next, response::Response, extract::{Query, State}, http::{HeaderMap, Uri, header::{AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL}}};
+use hyper::Request;
+
+use crate::{state::state::Mongo, types::errors::ApiError};
+
+pub const API_KEY_HEADER: &str = "apiKey";
+
+/// WebSocket subprotocol announcing that the next protocol in `Sec-WebSocket-Protocol` is a bearer token,
+/// e.g. `new WebSocket(url, ["bearer", token])`. `/ws` selects it so that browsers accept the upgrade.
+pub const BEARER_PROTOCOL: &str = "bearer";
+
+// Query parameter carrying a bearer token where headers cannot be set
+const ACCESS_TOKEN_PARAM: &str = "accessToken";
+
+// Browsers cannot set headers on a WebSocket upgrade or an EventSource, so these also take credentials
+// from the query string, or for WebSockets from `Sec-WebSocket-Protocol`
+const BROWSER_ROUTES: [&str; 2] = ["/ws", "/watch"];
+
+enum Credentials {
+    Bearer(String),
+    ApiKey(String)
+}
+
+/// Authenticates requests by an `Authorization: Bearer` token or their `apiKey` header, and attaches the `Principal`
+/// to the request, along with the token's `Claims`. A bearer token takes precedence when both are sent.
+/// Requests pass through unauthenticated when neither scheme is configured, which startup only allows with `AUTH_DISABLED`.
+pub async fn auth_mw<B>(State(state): State<Mongo>, mut req: Request<B>, next: Next<B>) -> Result<Response, ApiError> {
+    if !state.jwt.enabled() && !state.api_keys.enabled() {
+        return Ok(next.run(req).await);
+    }
+
+    let credentials = match header_credentials(req.headers())? {
+        Some(credentials) => Some(credentials),
+        None if BROWSER_ROUTES.contains(&req.uri().path()) => browser_credentials(req.uri(), req.headers()),
+        None => None
+    };
+
+    match credentials {
+        Some(Credentials::Bearer(token)) => {
+            if !state.jwt.enabled() {
+                return Err(ApiError::Unauthorized("Bearer tokens are not accepted".to_string()));
+            }
+
+            let (principal, claims) = state.jwt.verify(&token)?;
+            req.extensions_mut().insert(principal);
+            req.extensions_mut().insert(claims);
+        },
+        Some(Credentials::ApiKey(key)) => {
+            if !state.api_keys.enabled() {
+                return Err(ApiError::Unauthorized("API keys are not accepted".to_string()));
+            }
+
+            let principal = state.api_keys.authenticate(&key).await?;
+            req.extensions_mut().insert(principal);
+        },
+        None => return Err(ApiError::Unauthorized(format!("Missing {} or Authorization header", API_KEY_HEADER)))
+    }
+
+    Ok(next.run(req).await)
+}
+
+fn header_credentials(headers: &HeaderMap) -> Result<Option<Credentials>, ApiError> {
+    if let Some(header) = headers.get(AUTHORIZATION) {
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|val| val.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::Unauthorized("Authorization header must be 'Bearer <token>'".to_string()))?;
+
+        return Ok(Some(Credentials::Bearer(token.trim().to_string())));
+    }
+
+    match headers.get(API_KEY_HEADER).map(|val| val.to_str()) {
+        Some(Ok(key)) => Ok(Some(Credentials::ApiKey(key.to_string()))),
+        Some(Err(_)) => Err(ApiError::Unauthorized(format!("Invalid {} header", API_KEY_HEADER))),
+        None => Ok(None)
+    }
+}
+
+// `?accessToken=` or `?apiKey=`, or a token following the `bearer` subprotocol
+fn browser_credentials(uri: &Uri, headers: &HeaderMap) -> Option<Credentials> {
+    let params = Query::<HashMap<String, String>>::try_from_uri(uri).map(|Query(params)| params).unwrap_or_default();
+
+    if let Some(token) = params.get(ACCESS_TOKEN_PARAM) {
+        return Some(Credentials::Bearer(token.clone()));
+    }
+
+    if let Some(key) = params.get(API_KEY_HEADER) {
+        return Some(Credentials::ApiKey(key.clone()));
+    }
+
+    let protocols: Vec<&str> = headers
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|val| val.to_str().ok())
+        .map(|val| val.split(',').map(str::trim).collect())
+        .unwrap_or_default();
+
+    protocols
+        .iter()
+        .position(|protocol| *protocol == BEARER_PROTOCOL)
+        .and_then(|i| protocols.get(i + 1))
+        .map(|token| Credentials::Bearer(token.to_string()))
+}
@@ -1,28 +1,86 @@
-use axum::{middleware::Next, response::Response};
-use hyper::{Request, StatusCode, header::CONTENT_TYPE, http::HeaderValue};
+use axum::{middleware::Next, response::Response, body::Body};
+use hyper::{Request, header::{CONTENT_TYPE, ACCEPT}, http::HeaderValue};
+
+use crate::{middleware::mongo::bson_mw, types::errors::ApiError};
 
 const JSON: &str = "application/json";
 const EJSON: &str = "application/ejson";
+pub const NDJSON: &str = "application/x-ndjson";
+pub const BSON: &str = "application/bson";
+
+/// Extended JSON mode used for response bodies, negotiated from the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtJsonFormat {
+    Canonical,
+    Relaxed
+}
+
+impl ExtJsonFormat {
+    pub fn from_accept(accept: Option<&HeaderValue>) -> Self {
+        match accept.and_then(|val| val.to_str().ok()) {
+            Some(val) if val.split(',').any(|media| media.trim().starts_with(EJSON)) => ExtJsonFormat::Canonical,
+            _ => ExtJsonFormat::Relaxed
+        }
+    }
+}
+
+/// How `find` and `aggregate` send their documents, negotiated from the `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorEncoding {
+    /// A single JSON array, buffered in full before responding
+    Array,
+    /// Newline-delimited JSON streamed straight from the cursor
+    NdJson,
+    /// BSON documents back to back, each prefixed by its length as BSON documents are, streamed from the cursor
+    Bson
+}
+
+impl CursorEncoding {
+    fn from_accept(accept: Option<&HeaderValue>) -> Self {
+        match accept.and_then(|val| val.to_str().ok()) {
+            Some(val) if val.split(',').any(|media| media.trim().starts_with(BSON)) => CursorEncoding::Bson,
+            Some(val) if val.split(',').any(|media| media.trim().starts_with(NDJSON)) => CursorEncoding::NdJson,
+            _ => CursorEncoding::Array
+        }
+    }
+}
+
+pub async fn ejson_mw(mut req: Request<Body>, next: Next<Body>) -> Result<Response, ApiError> {
+    let format = ExtJsonFormat::from_accept(req.headers().get(ACCEPT));
+    let encoding = CursorEncoding::from_accept(req.headers().get(ACCEPT));
+    req.extensions_mut().insert(format);
+    req.extensions_mut().insert(encoding);
 
-pub async fn ejson_mw<B>(mut req: Request<B>, next: Next<B>) -> Result<Response, StatusCode> {
     if let Some(content_type) = req.headers().get(CONTENT_TYPE) {
         if let Ok(val) = content_type.to_str() {
             match val {
                 JSON => {
-                    return Ok(next.run(req).await)
+                    Ok(with_format(next.run(req).await, format))
                 },
                 EJSON => {
                     req.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_str(JSON).unwrap());
-                    return Ok(next.run(req).await)
+                    Ok(with_format(next.run(req).await, format))
+                },
+                BSON => {
+                    Ok(with_format(bson_mw(req, next).await?, format))
                 },
-                _ => {
-                    return Err(StatusCode::BAD_REQUEST);
+                other => {
+                    Err(ApiError::UnsupportedMediaType(other.to_string()))
                 }
             }
         } else {
-            return Err(StatusCode::BAD_REQUEST);
+            Err(ApiError::UnsupportedMediaType("<invalid>".to_string()))
         }
     } else {
-        return Err(StatusCode::BAD_REQUEST);
+        Err(ApiError::UnsupportedMediaType("<missing>".to_string()))
     }
+}
+
+// Labels JSON responses as EJSON when the client asked for canonical Extended JSON
+fn with_format(mut res: Response, format: ExtJsonFormat) -> Response {
+    if format == ExtJsonFormat::Canonical && res.headers().get(CONTENT_TYPE).map(|val| val == JSON).unwrap_or(false) {
+        res.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(EJSON));
+    }
+
+    res
 }
\ No newline at end of file
@@ -1,57 +1,118 @@
-use axum::{http::{Request, StatusCode}, middleware::Next, response::Response, body::Body, extract::State};
+use axum::{http::{Request, StatusCode, request::Parts}, middleware::Next, response::Response, body::Body, extract::State};
 use hyper;
-use mongodb::{Collection, bson::Document};
+use mongodb::{Collection, Database, bson::{Bson, Document}};
 use serde_json::Value;
 
-use crate::state::state::Mongo;
+use crate::{state::{state::Mongo, sessions::TransactionSession}, types::errors::ApiError};
 
-pub async fn collection_mw(state: State<Mongo>, req: Request<Body>, next: Next<Body>) -> Result<Response, StatusCode> {
+pub async fn collection_mw(state: State<Mongo>, req: Request<Body>, next: Next<Body>) -> Result<Response, ApiError> {
     let (mut parts, body) = req.into_parts();
     
     if let Ok(bytes) = hyper::body::to_bytes(body).await {
-        let json: Result<Value, serde_json::Error> = serde_json::from_slice(&bytes.clone());
+        let json = body_json(&parts, &bytes);
         
         match json {
             Ok(body) => {
+                let session_source = parts.extensions.get::<TransactionSession>().map(|session| session.data_source().to_string());
+                let data_source = match (optional_str(&body, "dataSource")?, session_source.as_deref()) {
+                    // Sessions are bound to one client, so the body cannot name a different data source
+                    (Some(requested), Some(bound)) if requested != bound => {
+                        return Err(ApiError::invalid_field("dataSource", "must match the session's dataSource"));
+                    },
+                    (requested, bound) => bound.or(requested)
+                };
+                let db_name = optional_str(&body, "database")?;
+
+                let db = state.database(data_source, db_name)?;
+
                 if let Some(collection) = body.get("collection") {
                     if let Some(coll_name) = collection.as_str() {
-                        let collection: Collection<Document> = state.db.collection(coll_name);
+                        let collection: Collection<Document> = db.collection(coll_name);
                         parts.extensions.insert(collection);
+                        parts.extensions.insert(state.query_policy.rules(db.name(), Some(coll_name)));
                         let new_req = Request::from_parts(parts, Body::from(bytes));
                         Ok(next.run(new_req).await)
                     } else {
-                        return Err(StatusCode::BAD_REQUEST);
+                        Err(ApiError::invalid_field("collection", "expected a string"))
                     }
                 } else {
-                    return Err(StatusCode::BAD_REQUEST);
+                    Err(ApiError::missing_field("collection"))
                 }
             },
-            Err(_) => {
-                return Err(StatusCode::BAD_REQUEST);
+            Err(e) => {
+                Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: e.to_string() })
             }
         }
     } else {
-        return Err(StatusCode::BAD_REQUEST);
+        Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: "Failed to read request body".to_string() })
     }
 }
 
+/// Like `collection_mw` but only resolves the `database`, for routes that are not scoped to a collection.
+pub async fn database_mw(state: State<Mongo>, req: Request<Body>, next: Next<Body>) -> Result<Response, ApiError> {
+    let (mut parts, body) = req.into_parts();
 
-// This function needs the same byte cloning treatment as collection_mw, but is not in use
-pub async fn bson_mw(mut req: Request<Body>, next: Next<Body>) -> Result<Response, StatusCode> {
-    if let Ok(bytes) = hyper::body::to_bytes(req.body_mut()).await {
-        let b: &[u8] = &*bytes;
-        let doc: Result<Document, serde_json::Error> = serde_json::from_slice(b);
+    if let Ok(bytes) = hyper::body::to_bytes(body).await {
+        let json = body_json(&parts, &bytes);
+
+        match json {
+            Ok(body) => {
+                let data_source = optional_str(&body, "dataSource")?;
+                let db_name = optional_str(&body, "database")?;
 
-        match doc {
-            Ok(d) => {
-                req.extensions_mut().insert(d);
-                return Ok(next.run(req).await);
+                let db: Database = state.database(data_source, db_name)?;
+                parts.extensions.insert(db);
+                let new_req = Request::from_parts(parts, Body::from(bytes));
+                Ok(next.run(new_req).await)
+            },
+            Err(e) => {
+                Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: e.to_string() })
+            }
+        }
+    } else {
+        Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: "Failed to read request body".to_string() })
+    }
+}
+
+fn optional_str<'a>(body: &'a Value, field: &'static str) -> Result<Option<&'a str>, ApiError> {
+    match body.get(field) {
+        Some(value) => match value.as_str() {
+            Some(s) => Ok(Some(s)),
+            None => Err(ApiError::invalid_field(field, "expected a string"))
+        },
+        None => Ok(None)
+    }
+}
+
+
+/// A BSON request body, decoded once by `bson_mw` for the middleware and extractors that read it.
+#[derive(Debug, Clone)]
+pub struct BsonPayload(pub Document);
+
+// BSON bodies were already decoded by bson_mw, the rest are JSON
+fn body_json(parts: &Parts, bytes: &[u8]) -> Result<Value, serde_json::Error> {
+    match parts.extensions.get::<BsonPayload>() {
+        Some(BsonPayload(doc)) => Ok(Bson::Document(doc.clone()).into_relaxed_extjson()),
+        None => serde_json::from_slice(bytes)
+    }
+}
+
+/// Decodes an `application/bson` body, run by `ejson_mw` for that content type.
+pub async fn bson_mw(req: Request<Body>, next: Next<Body>) -> Result<Response, ApiError> {
+    let (mut parts, body) = req.into_parts();
+
+    if let Ok(bytes) = hyper::body::to_bytes(body).await {
+        match Document::from_reader(&bytes[..]) {
+            Ok(doc) => {
+                parts.extensions.insert(BsonPayload(doc));
+                let new_req = Request::from_parts(parts, Body::from(bytes));
+                Ok(next.run(new_req).await)
             },
-            Err(_) => {
-                return Err(StatusCode::BAD_REQUEST);
+            Err(e) => {
+                Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: e.to_string() })
             }
         }
     } else {
-        return Err(StatusCode::BAD_REQUEST);
+        Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: "Failed to read request body".to_string() })
     }
 }
\ No newline at end of file
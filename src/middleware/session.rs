@@ -0,0 +1,20 @@
+use axum::{middleware::Next, response::Response, extract::{FromRequestParts, State}};
+use hyper::Request;
+
+use crate::{state::state::Mongo, types::errors::ApiError, utils::extract::{Caller, SessionToken, SESSION_TOKEN_HEADER}};
+
+/// Attaches the caller's `TransactionSession` when the request carries a `sessionToken` header,
+/// so that handlers run their operation inside that session's transaction.
+pub async fn session_mw<B>(State(state): State<Mongo>, req: Request<B>, next: Next<B>) -> Result<Response, ApiError> {
+    let (mut parts, body) = req.into_parts();
+
+    if parts.headers.contains_key(SESSION_TOKEN_HEADER) {
+        let SessionToken(token) = SessionToken::from_request_parts(&mut parts, &state).await?;
+        let Caller(caller) = Caller::from_request_parts(&mut parts, &state).await?;
+
+        let session = state.sessions.get(&token, &caller)?;
+        parts.extensions.insert(session);
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
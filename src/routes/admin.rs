@@ -0,0 +1,136 @@
+use std::{env, sync::Arc};
+
+use axum::{Router, Json, routing::post, middleware, Extension};
+use futures::TryStreamExt;
+use mongodb::{Collection, Database, IndexModel, bson::{self, doc, Document}, results::CollectionSpecification};
+use serde_json::{Value, json};
+
+use crate::{state::state::Mongo, middleware::{admin::{admin_mw, AdminKey}, mongo::{collection_mw, database_mw}, headers::{ejson_mw, ExtJsonFormat}}, utils::{mongo::bson_as_json, extract::Payload, query_policy::QueryRules}, types::{errors::ApiError, mongo::{requests::{create_index::CreateIndexRequest, create_indexes::CreateIndexesRequest, list_indexes::ListIndexesRequest, drop_index::DropIndexRequest, list_collections::ListCollectionsRequest, create_collection::CreateCollectionRequest, drop_collection::DropCollectionRequest, rename_collection::RenameCollectionRequest, list_databases::ListDatabasesRequest}, traits::requests::{MongoRequest, DocumentPayload, FilterQuery}}}};
+
+pub fn admin_router(state: Mongo) -> Router {
+    let admin_key = AdminKey(env::var("ADMIN_KEY").ok());
+
+    let collection_routes = Router::new()
+        .route("/createIndex", post(create_index))
+        .route("/createIndexes", post(create_indexes))
+        .route("/listIndexes", post(list_indexes))
+        .route("/dropIndex", post(drop_index))
+        .route("/dropCollection", post(drop_collection))
+        .route("/renameCollection", post(rename_collection))
+        .layer(middleware::from_fn_with_state(state.clone(), collection_mw));
+
+    let database_routes = Router::new()
+        .route("/listCollections", post(list_collections))
+        .route("/createCollection", post(create_collection))
+        .layer(middleware::from_fn_with_state(state.clone(), database_mw));
+
+    Router::new()
+        .route("/listDatabases", post(list_databases))
+        .merge(collection_routes)
+        .merge(database_routes)
+        .layer(Extension(state))
+        .layer(middleware::from_fn(ejson_mw))
+        .layer(middleware::from_fn_with_state(admin_key, admin_mw))
+}
+
+async fn create_index(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, Payload(body): Payload<CreateIndexRequest>) -> Result<Json<Value>, ApiError> {
+    let index = body.payload()?;
+    index.options.iter().try_for_each(|opts| rules.check(opts))?;
+
+    let res = db.create_index(index, body.opts()?).await?;
+
+    Ok(Json(json!({ "indexName": res.index_name })))
+}
+
+async fn create_indexes(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, Payload(body): Payload<CreateIndexesRequest>) -> Result<Json<Value>, ApiError> {
+    let indexes = body.payload()?;
+
+    for (i, index) in indexes.iter().enumerate() {
+        index.options.iter().try_for_each(|opts| rules.check(opts)).map_err(|e| e.nested(&format!("indexes.{}", i)))?;
+    }
+
+    let res = db.create_indexes(indexes, body.opts()?).await?;
+
+    Ok(Json(json!({ "indexNames": res.index_names })))
+}
+
+async fn list_indexes(db: Extension<Collection<Document>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<ListIndexesRequest>) -> Result<Json<Value>, ApiError> {
+    let indexes: Vec<IndexModel> = db.list_indexes(body.opts()?).await?.try_collect().await?;
+
+    let indexes = bson::to_bson(&indexes).map_err(mongodb::error::Error::from)?;
+
+    Ok(Json(bson_as_json(indexes, format)))
+}
+
+async fn drop_index(db: Extension<Collection<Document>>, Payload(body): Payload<DropIndexRequest>) -> Result<Json<Value>, ApiError> {
+    db.drop_index(body.name(), body.opts()?).await?;
+
+    Ok(Json(json!({ "dropped": body.name() })))
+}
+
+async fn drop_collection(db: Extension<Collection<Document>>, Payload(body): Payload<DropCollectionRequest>) -> Result<Json<Value>, ApiError> {
+    db.drop(body.opts()?).await?;
+
+    Ok(Json(json!({ "dropped": body.coll() })))
+}
+
+async fn rename_collection(Extension(state): Extension<Mongo>, db: Extension<Collection<Document>>, Payload(body): Payload<RenameCollectionRequest>) -> Result<Json<Value>, ApiError> {
+    let target = state.database(body.data_source(), body.to_database())?;
+    let opts = body.opts()?;
+
+    let from = db.namespace().to_string();
+    let to = format!("{}.{}", target.name(), body.to());
+
+    let mut command = doc! { "renameCollection": &from, "to": &to, "dropTarget": opts.drop_target };
+    if let Some(write_concern) = opts.write_concern {
+        command.insert("writeConcern", bson::to_bson(&write_concern).map_err(mongodb::error::Error::from)?);
+    }
+
+    state.client(body.data_source())?.database("admin").run_command(command, None).await?;
+
+    Ok(Json(json!({ "renamed": from, "to": to })))
+}
+
+async fn list_collections(db: Extension<Database>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<ListCollectionsRequest>) -> Result<Json<Value>, ApiError> {
+    let filter = body.filter().transpose()?;
+
+    if body.name_only() {
+        return Ok(Json(json!(db.list_collection_names(filter).await?)));
+    }
+
+    let collections: Vec<CollectionSpecification> = db.list_collections(filter, body.opts()).await?.try_collect().await?;
+
+    let collections = bson::to_bson(&collections).map_err(mongodb::error::Error::from)?;
+
+    Ok(Json(bson_as_json(collections, format)))
+}
+
+async fn create_collection(db: Extension<Database>, Payload(body): Payload<CreateCollectionRequest>) -> Result<Json<Value>, ApiError> {
+    db.create_collection(body.coll(), body.opts()?).await?;
+
+    Ok(Json(json!({ "created": body.coll() })))
+}
+
+// Only databases the API is allowed to reach are listed
+async fn list_databases(Extension(state): Extension<Mongo>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<ListDatabasesRequest>) -> Result<Json<Value>, ApiError> {
+    let client = state.client(body.data_source())?;
+    let filter = body.filter().transpose()?;
+
+    if body.name_only() {
+        let names: Vec<String> = client.list_database_names(filter, body.opts()).await?
+            .into_iter()
+            .filter(|name| state.is_allowed(name))
+            .collect();
+
+        return Ok(Json(json!(names)));
+    }
+
+    let databases: Vec<_> = client.list_databases(filter, body.opts()).await?
+        .into_iter()
+        .filter(|db| state.is_allowed(&db.name))
+        .collect();
+
+    let databases = bson::to_bson(&databases).map_err(mongodb::error::Error::from)?;
+
+    Ok(Json(bson_as_json(databases, format)))
+}
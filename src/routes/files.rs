@@ -0,0 +1,302 @@
+use std::{env, fmt::Display, io};
+
+use axum::{Router, Json, Extension, routing::{get, post}, body::{self, Body, Bytes, StreamBody}, extract::{DefaultBodyLimit, FromRequest, Multipart, Path}, http::{HeaderMap, HeaderValue, Request, StatusCode, header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE}}, response::{IntoResponse, Response}};
+use futures::{AsyncWriteExt, Stream, StreamExt, TryStreamExt};
+use mongodb::{Database, bson::{self, doc, oid::ObjectId, Bson, Document}, gridfs::FilesCollectionDocument, options::{FindOptions, GridFsUploadOptions}, GridFsUploadStream};
+use serde_json::{json, Value};
+
+use crate::{state::state::Mongo, middleware::headers::ExtJsonFormat, types::{errors::ApiError, mongo::{requests::files::{parse_metadata, BucketQuery, FindFilesRequest, UploadQuery}, traits::requests::FilterQuery}}, utils::{extract::{Params, Payload}, mongo::bson_as_json}};
+
+const OCTET_STREAM: &str = "application/octet-stream";
+const MULTIPART: &str = "multipart/form-data";
+
+/// Largest file `/files/upload` accepts, from `MAX_UPLOAD_BYTES`, unlimited when unset.
+#[derive(Debug, Clone, Copy)]
+struct UploadLimit(Option<u64>);
+
+/// GridFS routes that do not take a JSON body, so they are routed outside the content type check.
+/// `/files/find` is JSON and is routed with the other database routes.
+pub fn files_router() -> Router {
+    let upload_limit = env::var("MAX_UPLOAD_BYTES")
+        .map(|val| val.parse::<u64>().expect("Error: Failed to parse MAX_UPLOAD_BYTES from environment"))
+        .ok();
+
+    Router::new()
+        // Uploads are streamed to the bucket, so they are limited by `write_upload` rather than buffered under axum's limit
+        .route("/files/upload", post(upload).layer(DefaultBodyLimit::disable()))
+        .route("/files/:id", get(download).delete(delete))
+        .layer(Extension(UploadLimit(upload_limit)))
+}
+
+/// Uploads the raw request body, or the `file` field of a multipart form, to the bucket.
+async fn upload(Extension(state): Extension<Mongo>, Extension(UploadLimit(limit)): Extension<UploadLimit>, Params(query): Params<UploadQuery>, req: Request<Body>) -> Result<Json<Value>, ApiError> {
+    let bucket = state.database(query.data_source(), query.database())?.gridfs_bucket(query.opts());
+    let chunk_size_bytes = query.chunk_size_bytes()?;
+
+    let mut filename = query.filename().map(str::to_string);
+    let mut content_type = query.content_type().map(str::to_string);
+    let mut metadata = query.metadata().transpose()?;
+
+    let request_type = req.headers().get(CONTENT_TYPE).and_then(|val| val.to_str().ok()).map(str::to_string);
+
+    if !request_type.as_deref().map(|val| val.starts_with(MULTIPART)).unwrap_or(false) {
+        let filename = filename.ok_or_else(|| ApiError::missing_field("filename"))?;
+        let upload_opts = upload_opts(chunk_size_bytes, metadata, content_type.or(request_type));
+
+        let (id, length) = write_upload(bucket.open_upload_stream(&filename, upload_opts), req.into_body(), limit).await?;
+        return Ok(Json(uploaded(id, filename, length)));
+    }
+
+    let mut multipart = Multipart::from_request(req, &()).await
+        .map_err(|rejection| ApiError::InvalidBody { status: rejection.status(), message: rejection.body_text() })?;
+
+    // Fields are read in order, so `filename`, `contentType` and `metadata` must come before `file`
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("filename") => filename = Some(field.text().await.map_err(multipart_error)?),
+            Some("contentType") => content_type = Some(field.text().await.map_err(multipart_error)?),
+            Some("metadata") => metadata = Some(parse_metadata(&field.text().await.map_err(multipart_error)?)?),
+            Some("file") => {
+                let filename = filename.or_else(|| field.file_name().map(str::to_string)).ok_or_else(|| ApiError::missing_field("filename"))?;
+                let content_type = content_type.or_else(|| field.content_type().map(str::to_string));
+                let upload_opts = upload_opts(chunk_size_bytes, metadata, content_type);
+
+                let (id, length) = write_upload(bucket.open_upload_stream(&filename, upload_opts), field, limit).await?;
+                return Ok(Json(uploaded(id, filename, length)));
+            },
+            _ => continue
+        }
+    }
+
+    Err(ApiError::missing_field("file"))
+}
+
+/// Streams a file from the bucket, honouring a single `Range: bytes=...` so downloads can resume.
+async fn download(Extension(state): Extension<Mongo>, Path(id): Path<String>, Params(query): Params<BucketQuery>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let db = state.database(query.data_source(), query.database())?;
+    let bucket = db.gridfs_bucket(query.opts());
+
+    let file = bucket.find(doc! { "_id": file_id(&id) }, None).await?
+        .try_next().await?
+        .ok_or_else(|| ApiError::NotFound(format!("File '{}' not found", id)))?;
+
+    let content_type = file.metadata.as_ref()
+        .and_then(|metadata| metadata.get_str("contentType").ok())
+        .and_then(|val| HeaderValue::from_str(val).ok())
+        .unwrap_or(HeaderValue::from_static(OCTET_STREAM));
+
+    let range = match headers.get(RANGE).and_then(|val| val.to_str().ok()) {
+        Some(range) => match parse_range(range, file.length) {
+            Ok(range) => range,
+            Err(()) => {
+                let (_, body) = ApiError::BadRequest(format!("Range is outside the file's {} bytes", file.length)).into_parts();
+                return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(CONTENT_RANGE, format!("bytes */{}", file.length))], Json(body)).into_response());
+            }
+        },
+        None => None
+    };
+
+    let response = Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .header(ACCEPT_RANGES, "bytes");
+
+    if file.length == 0 {
+        return Ok(response.header(CONTENT_LENGTH, 0).body(body::boxed(Body::empty())).unwrap());
+    }
+
+    let (start, end) = range.unwrap_or((0, file.length - 1));
+    let response = match range {
+        Some(_) => response.status(StatusCode::PARTIAL_CONTENT).header(CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, file.length)),
+        None => response.status(StatusCode::OK)
+    };
+
+    let chunks = read_chunks(&db, query.bucket(), &file, start, end).await?;
+
+    Ok(response.header(CONTENT_LENGTH, end - start + 1).body(body::boxed(StreamBody::new(chunks))).unwrap())
+}
+
+async fn delete(Extension(state): Extension<Mongo>, Path(id): Path<String>, Params(query): Params<BucketQuery>) -> Result<Json<Value>, ApiError> {
+    let bucket = state.database(query.data_source(), query.database())?.gridfs_bucket(query.opts());
+
+    bucket.delete(file_id(&id)).await?;
+
+    Ok(Json(json!({ "deleted": true })))
+}
+
+/// Finds files by their `fs.files` entry, e.g. `{"filter": {"metadata.owner": "..."}}`.
+pub async fn find_files(db: Extension<Database>, Extension(state): Extension<Mongo>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<FindFilesRequest>) -> Result<Json<Value>, ApiError> {
+    let bucket = db.gridfs_bucket(body.bucket_opts());
+    let filter = body.filter().transpose()?.unwrap_or_default();
+    state.query_policy.rules(db.name(), Some(&format!("{}.files", body.bucket()))).check_filter(&filter)?;
+
+    let files: Vec<FilesCollectionDocument> = bucket.find(filter, body.opts()?).await?.try_collect().await?;
+
+    let files = files
+        .iter()
+        .map(|file| bson::to_bson(file).map(|file| bson_as_json(file, format)))
+        .collect::<Result<Vec<Value>, _>>()
+        .map_err(mongodb::error::Error::from)?;
+
+    Ok(Json(Value::Array(files)))
+}
+
+async fn write_upload<E: Display>(mut upload: GridFsUploadStream, chunks: impl Stream<Item = Result<Bytes, E>>, limit: Option<u64>) -> Result<(Bson, u64), ApiError> {
+    futures::pin_mut!(chunks);
+    let mut length = 0;
+
+    while let Some(chunk) = chunks.next().await {
+        let written = match chunk {
+            Ok(bytes) if limit.map(|limit| length + bytes.len() as u64 > limit).unwrap_or(false) => {
+                Err(ApiError::InvalidBody { status: StatusCode::PAYLOAD_TOO_LARGE, message: format!("File is larger than {} bytes", limit.unwrap_or_default()) })
+            },
+            Ok(bytes) => {
+                length += bytes.len() as u64;
+                upload.write_all(&bytes).await.map_err(|e| ApiError::from(mongodb::error::Error::from(e)))
+            },
+            Err(e) => Err(ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: e.to_string() })
+        };
+
+        if let Err(err) = written {
+            // Removes the chunks written so far
+            let _ = upload.abort().await;
+            return Err(err);
+        }
+    }
+
+    upload.close().await.map_err(mongodb::error::Error::from)?;
+
+    Ok((upload.id().clone(), length))
+}
+
+// Reads the chunks covering bytes `start..=end` straight from the chunks collection, so a range
+// does not have to read through the start of the file
+async fn read_chunks(db: &Database, bucket: &str, file: &FilesCollectionDocument, start: u64, end: u64) -> Result<impl Stream<Item = Result<Bytes, io::Error>>, ApiError> {
+    let chunk_size = file.chunk_size_bytes as u64;
+
+    if chunk_size == 0 {
+        let invalid = io::Error::new(io::ErrorKind::InvalidData, format!("file {} has a chunkSize of 0", file.id));
+        return Err(mongodb::error::Error::from(invalid).into());
+    }
+
+    let (first, last) = (start / chunk_size, end / chunk_size);
+
+    let filter = doc! { "files_id": file.id.clone(), "n": { "$gte": first as i64, "$lte": last as i64 } };
+    let opts = FindOptions::builder().sort(doc! { "n": 1 }).build();
+    let cursor = db.collection::<Document>(&format!("{}.chunks", bucket)).find(filter, opts).await?;
+
+    Ok(cursor.enumerate().map(move |(i, chunk)| {
+        let chunk = chunk.map_err(io::Error::other)?;
+        let n = first + i as u64;
+
+        let chunk_n = match chunk.get("n") {
+            Some(Bson::Int32(n)) => Some(*n as u64),
+            Some(Bson::Int64(n)) => Some(*n as u64),
+            _ => None
+        };
+
+        if chunk_n != Some(n) {
+            return Err(io::Error::other(format!("chunk {} of the file is missing", n)));
+        }
+
+        let data = chunk.get_binary_generic("data").map_err(io::Error::other)?;
+        let offset = n * chunk_size;
+        let from = (start.saturating_sub(offset) as usize).min(data.len());
+        let to = ((end + 1 - offset) as usize).min(data.len());
+
+        Ok(Bytes::copy_from_slice(&data[from..to]))
+    }))
+}
+
+// `Ok(None)` for ranges that are ignored (multiple ranges, other units), `Err` for unsatisfiable ones
+fn parse_range(header: &str, length: u64) -> Result<Option<(u64, u64)>, ()> {
+    let spec = match header.strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return Ok(None)
+    };
+
+    let (start, end) = match spec.split_once('-') {
+        Some(bounds) => bounds,
+        None => return Ok(None)
+    };
+
+    let last = length.saturating_sub(1);
+    let (start, end) = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        (Some(start), Some(end)) if start <= end => (start, end.min(last)),
+        (Some(start), None) if end.is_empty() => (start, last),
+        (None, Some(suffix)) if start.is_empty() && suffix > 0 => (length.saturating_sub(suffix), last),
+        _ => return Ok(None)
+    };
+
+    if start >= length {
+        return Err(());
+    }
+
+    Ok(Some((start, end)))
+}
+
+// fs.files no longer has a contentType field, so it is kept in the metadata
+fn upload_opts(chunk_size_bytes: Option<u32>, metadata: Option<Document>, content_type: Option<String>) -> GridFsUploadOptions {
+    let mut metadata = metadata.unwrap_or_default();
+
+    if let Some(content_type) = content_type {
+        metadata.insert("contentType", content_type);
+    }
+
+    let mut upload_opts = GridFsUploadOptions::default();
+    upload_opts.chunk_size_bytes = chunk_size_bytes;
+    upload_opts.metadata = if metadata.is_empty() { None } else { Some(metadata) };
+
+    upload_opts
+}
+
+fn uploaded(id: Bson, filename: String, length: u64) -> Value {
+    json!({ "id": bson_as_json(id, ExtJsonFormat::Relaxed), "filename": filename, "length": length })
+}
+
+// Ids are ObjectIds unless the file was stored under another id
+fn file_id(id: &str) -> Bson {
+    ObjectId::parse_str(id).map(Bson::ObjectId).unwrap_or_else(|_| Bson::String(id.to_string()))
+}
+
+fn multipart_error(err: impl Display) -> ApiError {
+    ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: err.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn parses_bounded_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=900-5000", 1000), Ok(Some((900, 999))));
+    }
+
+    #[test]
+    fn parses_open_ended_ranges() {
+        assert_eq!(parse_range("bytes=100-", 1000), Ok(Some((100, 999))));
+        assert_eq!(parse_range("bytes=999-", 1000), Ok(Some((999, 999))));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some((900, 999))));
+        assert_eq!(parse_range("bytes=-5000", 1000), Ok(Some((0, 999))));
+    }
+
+    #[test]
+    fn rejects_ranges_past_the_end() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(()));
+        assert_eq!(parse_range("bytes=1000-1100", 1000), Err(()));
+        assert_eq!(parse_range("bytes=0-", 0), Err(()));
+    }
+
+    #[test]
+    fn ignores_unsupported_ranges() {
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=10-5", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=-0", 1000), Ok(None));
+        assert_eq!(parse_range("items=0-1", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=abc", 1000), Ok(None));
+    }
+}
@@ -1,15 +1,22 @@
-use axum::{Router, Json, routing::post, http::StatusCode, middleware, Extension};
-use mongodb::{Collection, bson::{Document, self}, results::{InsertOneResult, InsertManyResult, UpdateResult, DeleteResult}, options::ReplaceOptions};
-use serde_json::Value;
+use std::{collections::HashSet, convert::Infallible, sync::Arc};
 
-use crate::{state::state::Mongo, middleware::{mongo::collection_mw, headers::ejson_mw}, utils::mongo::docs_as_json, types::mongo::{requests::{find::FindRequest, find_one::FindOneRequest, insert_one::InsertOneRequest, insert_many::InsertManyRequest, update::{UpdateRequest, UpdateOptionsWrapper}, delete::DeleteRequest, aggregate::AggregateRequest}, traits::requests::{FilterQuery, MongoRequest, DocumentPayload}}};
+use axum::{Router, Json, routing::{get, post}, middleware, Extension, http::{HeaderMap, header::ACCEPT}, response::{IntoResponse, Response, sse::{Event, KeepAlive, Sse}}};
+use futures::{stream, Stream, StreamExt};
+use mongodb::{Collection, error::{ErrorKind, WriteFailure}, bson::{doc, oid::ObjectId, Document, Bson, self}, results::UpdateResult, options::{InsertManyOptions, InsertOneOptions, ReplaceOptions}};
+use serde_json::{Value, json};
 
-pub async fn mongo_router() -> Router {
-    let state = Mongo::new().await;
+use crate::{state::{state::Mongo, sessions::TransactionSession, cursors::batch_size}, routes::{ws::{ws, SubscriptionLimit}, files::{files_router, find_files}}, middleware::{auth::auth_mw, mongo::{collection_mw, database_mw}, session::session_mw, headers::{ejson_mw, ExtJsonFormat, CursorEncoding}}, utils::{mongo::{docs_as_json, docs_as_ndjson, docs_as_bson, doc_as_json, doc_as_bson, bson_as_json, required_filter, parse_resume_token, resume_token_id}, extract::{Params, Payload, Caller, SessionToken}, pagination::find_page, query_policy::QueryRules, session::run_transaction}, types::{errors::ApiError, mongo::{requests::{find::FindRequest, find_one::FindOneRequest, find_one_and_update::FindOneAndUpdateRequest, find_one_and_replace::FindOneAndReplaceRequest, find_one_and_delete::FindOneAndDeleteRequest, insert_one::InsertOneRequest, insert_many::InsertManyRequest, update::{UpdateRequest, UpdateOptionsWrapper}, delete::DeleteRequest, aggregate::AggregateRequest, count_documents::CountDocumentsRequest, estimated_document_count::EstimatedDocumentCountRequest, distinct::DistinctRequest, bulk_write::{BulkWriteRequest, WriteOp}, transaction::{TransactionRequest, TransactionOperation}, start_session::StartSessionRequest, get_more::GetMoreRequest, kill_cursors::KillCursorsRequest, watch::WatchQuery}, operation::Operation, responses::bulk_write::BulkWriteResult, traits::requests::{FilterQuery, MongoRequest, DocumentPayload}}}};
 
-    Router::new()
+// Sent by EventSource clients when reconnecting, holding the `id` of the last event they received
+const LAST_EVENT_ID: &str = "Last-Event-ID";
+
+pub fn mongo_router(state: Mongo) -> Router {
+    let collection_routes = Router::new()
         .route("/find", post(find))
         .route("/findOne", post(find_one))
+        .route("/findOneAndUpdate", post(find_one_and_update))
+        .route("/findOneAndReplace", post(find_one_and_replace))
+        .route("/findOneAndDelete", post(find_one_and_delete))
         .route("/insertOne", post(insert_one))
         .route("/insertMany", post(insert_many))
         .route("/updateOne", post(update_one))
@@ -18,186 +25,604 @@ pub async fn mongo_router() -> Router {
         .route("/deleteOne", post(delete_one))
         .route("/deleteMany", post(delete_many))
         .route("/aggregate", post(aggregate))
-        .layer(middleware::from_fn_with_state(state, collection_mw))
+        .route("/countDocuments", post(count_documents))
+        .route("/estimatedDocumentCount", post(estimated_document_count))
+        .route("/distinct", post(distinct))
+        .route("/bulkWrite", post(bulk_write))
+        .layer(middleware::from_fn_with_state(state.clone(), collection_mw))
+        .layer(middleware::from_fn_with_state(state.clone(), session_mw));
+
+    let database_routes = Router::new()
+        .route("/files/find", post(find_files))
+        .layer(middleware::from_fn_with_state(state.clone(), database_mw));
+
+    Router::new()
+        .route("/transaction", post(transaction))
+        .route("/session/start", post(start_session))
+        .route("/session/commit", post(commit_session))
+        .route("/session/abort", post(abort_session))
+        .route("/getMore", post(get_more))
+        .route("/killCursors", post(kill_cursors))
+        .merge(collection_routes)
+        .merge(database_routes)
         .layer(middleware::from_fn(ejson_mw))
+        // WebSocket upgrades, change streams and file transfers do not have JSON bodies, so they are routed outside the content type check
+        .route("/ws", get(ws).layer(Extension(SubscriptionLimit::from_env())))
+        .route("/watch", get(watch))
+        .merge(files_router())
+        .layer(Extension(state.clone()))
+        .layer(middleware::from_fn_with_state(state, auth_mw))
 }
 
+#[allow(clippy::too_many_arguments)]
+async fn find(db: Extension<Collection<Document>>, Extension(state): Extension<Mongo>, Extension(rules): Extension<Arc<QueryRules>>, Caller(caller): Caller, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Extension(encoding): Extension<CursorEncoding>, Payload(body): Payload<FindRequest>) -> Result<Response, ApiError> {
+    let filter = body.filter().transpose()?;
+    filter.iter().try_for_each(|filter| rules.check_filter(filter))?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+    let batch_size = batch_size(body.batch_size())?;
 
-async fn find(db: Extension<Collection<Document>>, Json(body): Json<FindRequest>) -> Result<Json<Value>, StatusCode> {
-    let filter = match body.filter() {
-        Some(Ok(f)) => Some(f),
-        None => None,
-        _ => {
-            return Err(StatusCode::BAD_REQUEST);
+    if let Some(Extension(session)) = session {
+        if body.cursor() {
+            return Err(ApiError::BadRequest("Cursors cannot be used in a session".to_string()));
         }
-    };
-    
-    if let Ok(cursor) = db.find(filter, body.opts()).await {
-        if let Ok(results) = docs_as_json(cursor).await {
-            Ok(Json(results))
-        } else {
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+
+        if body.page_size().is_some() || body.page_token().is_some() {
+            return Err(ApiError::BadRequest("Paging cannot be used in a session".to_string()));
         }
-    } else {
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    } 
-}
 
-async fn find_one(db: Extension<Collection<Document>>, Json(body): Json<FindOneRequest>) -> Result<Json<Document>, StatusCode> {
-    let filter = match body.filter() {
-        Some(Ok(f)) => Some(f),
-        None => None,
-        _ => {
-            return Err(StatusCode::BAD_REQUEST);
+        return Ok(Json(session.run(&db, Operation::Find(filter, opts), format).await?).into_response());
+    }
+
+    if let Some(page_size) = body.page_size() {
+        if body.cursor() {
+            return Err(ApiError::invalid_field("cursor", "cannot be combined with pageSize"));
         }
-    };
 
-    let doc: Result<Option<Document>, mongodb::error::Error> = db.find_one(filter, body.opts()).await;
-    
-    match doc {
-        Ok(Some(result)) => Ok(Json(result)),
-        Ok(None) => Ok(Json(bson::Document::new())),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+        let (docs, next_page_token) = find_page(&db, filter, opts, page_size, body.page_token(), &state.page_signer).await?;
+
+        return batch_response(doc! { "documents": docs, "nextPageToken": next_page_token }, encoding, format);
+    }
+
+    if body.page_token().is_some() {
+        return Err(ApiError::missing_field("pageSize"));
+    }
+
+    let cursor = db.find(filter, opts).await?;
+
+    if body.cursor() {
+        let (cursor_id, docs) = state.cursors.first_batch(&caller, cursor, batch_size).await?;
+        return batch_response(doc! { "cursorId": cursor_id, "documents": docs }, encoding, format);
+    }
+
+    if encoding == CursorEncoding::Bson {
+        return Ok(docs_as_bson(cursor));
+    }
+
+    if encoding == CursorEncoding::NdJson || body.stream() {
+        return Ok(docs_as_ndjson(cursor, format));
     }
+
+    Ok(Json(docs_as_json(cursor, format).await?).into_response())
 }
 
-async fn insert_one(db: Extension<Collection<Document>>, Json(body): Json<InsertOneRequest>) -> Result<Json<InsertOneResult>, StatusCode> {
-    let doc = match body.payload() {
-        Ok(d) => d,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST)
-        }
-    };
+async fn find_one(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<FindOneRequest>) -> Result<Json<Value>, ApiError> {
+    let filter = body.filter().transpose()?;
+    filter.iter().try_for_each(|filter| rules.check_filter(filter))?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
 
-    match db.insert_one(doc, body.opts()).await {
-        Ok(r) => Ok(Json(r)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::FindOne(filter, opts), format).await?));
     }
-} 
 
-async fn insert_many(db: Extension<Collection<Document>>, Json(body): Json<InsertManyRequest>) -> Result<Json<InsertManyResult>, StatusCode> {
-    let docs = match body.payload() {
-        Ok(d) => d,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
+    match db.find_one(filter, opts).await? {
+        Some(result) => Ok(Json(doc_as_json(result, format))),
+        None => Ok(Json(doc_as_json(bson::Document::new(), format)))
+    }
+}
+
+async fn find_one_and_update(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<FindOneAndUpdateRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let update = body.payload()?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::FindOneAndUpdate(query, update, opts), format).await?));
+    }
 
-    match db.insert_many(docs, body.opts()).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    match db.find_one_and_update(query, update, opts).await? {
+        Some(result) => Ok(Json(doc_as_json(result, format))),
+        None => Ok(Json(doc_as_json(bson::Document::new(), format)))
     }
 }
 
-async fn update_one(db: Extension<Collection<Document>>, Json(body): Json<UpdateRequest>) -> Result<Json<UpdateResult>, StatusCode> {
-    let query = match body.filter() {
-        Some(Ok(doc)) => doc,
-        Some(Err(_)) | None => {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
+async fn find_one_and_replace(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<FindOneAndReplaceRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let replacement = body.payload()?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
 
-    let update = match body.payload() {
-        Ok(doc) => doc,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST);
-        } 
-    };
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::FindOneAndReplace(query, replacement, opts), format).await?));
+    }
 
-    match db.update_one(query, update, body.opts()).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    match db.find_one_and_replace(query, replacement, opts).await? {
+        Some(result) => Ok(Json(doc_as_json(result, format))),
+        None => Ok(Json(doc_as_json(bson::Document::new(), format)))
     }
 }
 
-async fn update_many(db: Extension<Collection<Document>>, Json(body): Json<UpdateRequest>) -> Result<Json<UpdateResult>, StatusCode> {
-    let query = match body.filter() {
-        Some(Ok(doc)) => doc,
-        Some(Err(_)) | None => {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
+async fn find_one_and_delete(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<FindOneAndDeleteRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
 
-    let update = match body.payload() {
-        Ok(doc) => doc,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST);
-        } 
-    };
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::FindOneAndDelete(query, opts), format).await?));
+    }
 
-    match db.update_many(query, update, body.opts()).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    match db.find_one_and_delete(query, opts).await? {
+        Some(result) => Ok(Json(doc_as_json(result, format))),
+        None => Ok(Json(doc_as_json(bson::Document::new(), format)))
     }
 }
 
-async fn replace_one(db: Extension<Collection<Document>>, Json(body): Json<UpdateRequest>) -> Result<Json<UpdateResult>, StatusCode> {
-    let query = match body.filter() {
-        Some(Ok(doc)) => doc,
-        Some(Err(_)) | None => {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
+/// Continues a cursor opened with `"cursor": true`, `cursorId` is null once it is exhausted.
+async fn get_more(Extension(state): Extension<Mongo>, Extension(format): Extension<ExtJsonFormat>, Extension(encoding): Extension<CursorEncoding>, Caller(caller): Caller, Payload(body): Payload<GetMoreRequest>) -> Result<Response, ApiError> {
+    let (cursor_id, docs) = state.cursors.get_more(body.cursor_id(), &caller, batch_size(body.batch_size())?).await?;
 
-    let replacement = match body.payload() {
-        Ok(doc) => doc,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST);
-        } 
-    };
+    batch_response(doc! { "cursorId": cursor_id, "documents": docs }, encoding, format)
+}
+
+async fn kill_cursors(Extension(state): Extension<Mongo>, Caller(caller): Caller, Payload(body): Payload<KillCursorsRequest>) -> Result<Json<Value>, ApiError> {
+    let (killed, not_found): (Vec<&String>, Vec<&String>) = body.cursor_ids().iter().partition(|id| state.cursors.kill(id, &caller));
 
-    let opts: Option<ReplaceOptions> = Some(UpdateOptionsWrapper(body.opts()).into());
+    Ok(Json(json!({ "cursorsKilled": killed, "cursorsNotFound": not_found })))
+}
+
+// Cursor batches and pages are a single document, sent as BSON when it was asked for
+fn batch_response(batch: Document, encoding: CursorEncoding, format: ExtJsonFormat) -> Result<Response, ApiError> {
+    match encoding {
+        CursorEncoding::Bson => doc_as_bson(batch),
+        _ => Ok(Json(doc_as_json(batch, format)).into_response())
+    }
+}
+
+async fn insert_one(db: Extension<Collection<Document>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<InsertOneRequest>) -> Result<Json<Value>, ApiError> {
+    let doc = body.payload()?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::InsertOne(doc, body.opts()?), format).await?));
+    }
+
+    Ok(Json(json!(db.insert_one(doc, body.opts()?).await?)))
+}
+
+async fn insert_many(db: Extension<Collection<Document>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<InsertManyRequest>) -> Result<Json<Value>, ApiError> {
+    let docs = body.payload()?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::InsertMany(docs, body.opts()?), format).await?));
+    }
+
+    Ok(Json(json!(db.insert_many(docs, body.opts()?).await?)))
+}
+
+async fn update_one(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<UpdateRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let update = body.update()?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::UpdateOne(query, update, opts), format).await?));
+    }
+
+    Ok(Json(json!(db.update_one(query, update, opts).await?)))
+}
+
+async fn update_many(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<UpdateRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let update = body.update()?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::UpdateMany(query, update, opts), format).await?));
+    }
+
+    Ok(Json(json!(db.update_many(query, update, opts).await?)))
+}
+
+async fn replace_one(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<UpdateRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let replacement = body.replacement()?;
+
+    let opts: ReplaceOptions = UpdateOptionsWrapper(body.opts()?).try_into()?;
+    rules.check(&opts)?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::ReplaceOne(query, replacement, opts), format).await?));
+    }
+
+    Ok(Json(json!(db.replace_one(query, replacement, opts).await?)))
+}
+
+async fn delete_one(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<DeleteRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
 
-    match db.replace_one(query, replacement, opts).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::DeleteOne(query, opts), format).await?));
     }
+
+    Ok(Json(json!(db.delete_one(query, opts).await?)))
 }
 
-async fn delete_one(db: Extension<Collection<Document>>, Json(body): Json<DeleteRequest>) -> Result<Json<DeleteResult>, StatusCode> {
-    let query = match body.filter() {
-        Some(Ok(doc)) => doc,
-        Some(Err(_)) | None => {
-            return Err(StatusCode::BAD_REQUEST);
+async fn delete_many(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<DeleteRequest>) -> Result<Json<Value>, ApiError> {
+    let query = required_filter(&body)?;
+    rules.check_filter(&query)?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::DeleteMany(query, opts), format).await?));
+    }
+
+    Ok(Json(json!(db.delete_many(query, opts).await?)))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn aggregate(db: Extension<Collection<Document>>, Extension(state): Extension<Mongo>, Extension(rules): Extension<Arc<QueryRules>>, Caller(caller): Caller, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Extension(encoding): Extension<CursorEncoding>, Payload(body): Payload<AggregateRequest>) -> Result<Response, ApiError> {
+    let pipeline = body.payload()?;
+    state.check_pipeline(&rules, &db.namespace().db, &pipeline)?;
+    rules.check_pipeline(&pipeline)?;
+    let opts = body.opts()?;
+    rules.check(&opts)?;
+    let batch_size = batch_size(body.batch_size())?;
+
+    if let Some(Extension(session)) = session {
+        if body.cursor() {
+            return Err(ApiError::BadRequest("Cursors cannot be used in a session".to_string()));
         }
-    };
 
-    match db.delete_one(query, body.opts()).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+        return Ok(Json(session.run(&db, Operation::Aggregate(pipeline, opts), format).await?).into_response());
+    }
+
+    let cursor = db.aggregate(pipeline, opts).await?;
+
+    if body.cursor() {
+        let (cursor_id, docs) = state.cursors.first_batch(&caller, cursor, batch_size).await?;
+        return batch_response(doc! { "cursorId": cursor_id, "documents": docs }, encoding, format);
+    }
+
+    if encoding == CursorEncoding::Bson {
+        return Ok(docs_as_bson(cursor));
+    }
+
+    if encoding == CursorEncoding::NdJson || body.stream() {
+        return Ok(docs_as_ndjson(cursor, format));
     }
+
+    Ok(Json(docs_as_json(cursor, format).await?).into_response())
+}
+
+async fn count_documents(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<CountDocumentsRequest>) -> Result<Json<Value>, ApiError> {
+    let filter = body.filter().transpose()?;
+    filter.iter().try_for_each(|filter| rules.check_filter(filter))?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::CountDocuments(filter, body.opts()?), format).await?));
+    }
+
+    let count = db.count_documents(filter, body.opts()?).await?;
+
+    Ok(Json(json!({ "count": count })))
+}
+
+// The count command is not allowed in a transaction, so there is no session variant
+async fn estimated_document_count(db: Extension<Collection<Document>>, session: Option<Extension<TransactionSession>>, Payload(body): Payload<EstimatedDocumentCountRequest>) -> Result<Json<Value>, ApiError> {
+    if session.is_some() {
+        return Err(ApiError::BadRequest("estimatedDocumentCount cannot run in a session".to_string()));
+    }
+
+    let count = db.estimated_document_count(body.opts()?).await?;
+
+    Ok(Json(json!({ "count": count })))
 }
 
-async fn delete_many(db: Extension<Collection<Document>>, Json(body): Json<DeleteRequest>) -> Result<Json<DeleteResult>, StatusCode> {
-    let query = match body.filter() {
-        Some(Ok(doc)) => doc,
-        Some(Err(_)) | None => {
-            return Err(StatusCode::BAD_REQUEST);
+async fn distinct(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<DistinctRequest>) -> Result<Json<Value>, ApiError> {
+    let filter = body.filter().transpose()?;
+    filter.iter().try_for_each(|filter| rules.check_filter(filter))?;
+
+    if let Some(Extension(session)) = session {
+        return Ok(Json(session.run(&db, Operation::Distinct(body.key().to_string(), filter, body.opts()?), format).await?));
+    }
+
+    let values = db.distinct(body.key(), filter, body.opts()?).await?;
+
+    Ok(Json(json!({ "values": bson_as_json(Bson::Array(values), format) })))
+}
+
+/// Streams change events of the collection, or of the whole database without one, as Server-Sent Events.
+/// Each event carries its resume token as the SSE `id`, so a client reconnecting with `Last-Event-ID`, or opening
+/// the stream again with `resumeAfter`, resumes right after the last event it received.
+async fn watch(Extension(state): Extension<Mongo>, headers: HeaderMap, Params(query): Params<WatchQuery>) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let db = state.database(query.data_source(), query.database())?;
+    let format = ExtJsonFormat::from_accept(headers.get(ACCEPT));
+
+    let pipeline = query.payload()?;
+    state.query_policy.rules(db.name(), query.coll()).check_pipeline(&pipeline)?;
+
+    let mut opts = query.opts();
+
+    // The header is newer, EventSource updates it on every reconnect while the URL stays as first opened
+    if let Some(last_event_id) = headers.get(LAST_EVENT_ID) {
+        let last_event_id = last_event_id.to_str().map_err(|e| ApiError::invalid_field(LAST_EVENT_ID, e))?;
+        opts.resume_after = Some(parse_resume_token(LAST_EVENT_ID, last_event_id)?);
+    } else if let Some(resume_after) = query.resume_after() {
+        opts.resume_after = Some(parse_resume_token("resumeAfter", resume_after)?);
+    }
+
+    let changes = match query.coll() {
+        Some(coll) => db.collection::<Document>(coll).watch(pipeline, opts).await?.with_type::<Document>(),
+        None => db.watch(pipeline, opts).await?.with_type::<Document>()
+    };
+
+    let events = stream::unfold(Some(changes), move |changes| async move {
+        let mut changes = changes?;
+
+        match changes.next().await? {
+            Ok(change) => Some((Ok(change_event(change, format)), Some(changes))),
+            // The stream cannot continue after an error, report it as an `error` event and close it
+            Err(err) => Some((Ok(error_event(err.into())), None))
         }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn change_event(change: Document, format: ExtJsonFormat) -> Event {
+    let event = match change.get("_id") {
+        Some(token) => Event::default().id(resume_token_id(token.clone())),
+        None => Event::default()
     };
 
-    match db.delete_many(query, body.opts()).await {
-        Ok(res) => Ok(Json(res)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+    event.data(doc_as_json(change, format).to_string())
+}
+
+fn error_event(err: ApiError) -> Event {
+    let (_, body) = err.into_parts();
+
+    Event::default().event("error").data(body.to_string())
+}
+
+/// Runs each operation in turn against the collection. Ordered batches stop at the first failed operation,
+/// unordered ones carry on and report every failed operation in `writeErrors`. Either way the counts and ids of
+/// the operations that did run are returned.
+async fn bulk_write(db: Extension<Collection<Document>>, Extension(rules): Extension<Arc<QueryRules>>, session: Option<Extension<TransactionSession>>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<BulkWriteRequest>) -> Result<Json<BulkWriteResult>, ApiError> {
+    if session.is_some() {
+        return Err(ApiError::BadRequest("bulkWrite cannot run in a session, send its operations individually".to_string()));
+    }
+
+    let ops = body.payload()?;
+    let opts = body.opts()?;
+
+    for (i, op) in ops.iter().enumerate() {
+        rules.check(op).map_err(|e| e.nested(&format!("operations.{}", i)))?;
+    }
+
+    let mut result = BulkWriteResult::default();
+    let mut ops = ops.into_iter().enumerate().peekable();
+
+    while let Some((index, op)) = ops.next() {
+        let outcome = match op {
+            // Consecutive inserts share their options, so they are sent as one insertMany
+            WriteOp::InsertOne(doc, insert_opts) => {
+                let mut run = vec![(index, doc)];
+                while let Some((_, WriteOp::InsertOne(..))) = ops.peek() {
+                    if let Some((index, WriteOp::InsertOne(doc, _))) = ops.next() {
+                        run.push((index, doc));
+                    }
+                }
+
+                if !insert_run(&db, run, insert_opts, opts.ordered, &mut result, format).await && opts.ordered {
+                    break;
+                }
+                continue;
+            },
+            WriteOp::UpdateOne(query, update, opts) => db.update_one(query, update, opts).await.map(|res| {
+                record_update(&mut result, index, res, format);
+            }),
+            WriteOp::UpdateMany(query, update, opts) => db.update_many(query, update, opts).await.map(|res| {
+                record_update(&mut result, index, res, format);
+            }),
+            WriteOp::ReplaceOne(query, replacement, opts) => db.replace_one(query, replacement, opts).await.map(|res| {
+                record_update(&mut result, index, res, format);
+            }),
+            WriteOp::DeleteOne(query, opts) => db.delete_one(query, opts).await.map(|res| {
+                result.deleted_count += res.deleted_count;
+            }),
+            WriteOp::DeleteMany(query, opts) => db.delete_many(query, opts).await.map(|res| {
+                result.deleted_count += res.deleted_count;
+            })
+        };
+
+        if let Err(err) = outcome {
+            match err.kind.as_ref() {
+                ErrorKind::Write(WriteFailure::WriteError(e)) => {
+                    result.write_errors.push(json!({ "index": index, "code": e.code, "codeName": e.code_name, "message": e.message, "errInfo": e.details }));
+                },
+                // The write itself was applied, only its acknowledgement is missing
+                ErrorKind::Write(WriteFailure::WriteConcernError(e)) => {
+                    result.write_concern_errors.push(json!({ "index": index, "code": e.code, "codeName": e.code_name, "message": e.message, "errInfo": e.details }));
+                    continue;
+                },
+                _ => {
+                    let (_, body) = ApiError::from(err).into_parts();
+                    result.write_errors.push(json!({ "index": index, "errorCode": body["errorCode"], "message": body["error"], "details": body.get("details") }));
+                }
+            }
+
+            if opts.ordered {
+                break;
+            }
+        }
     }
+
+    Ok(Json(result))
 }
 
-async fn aggregate(db: Extension<Collection<Document>>, Json(body): Json<AggregateRequest>) -> Result<Json<Value>, StatusCode> {
-    let pipeline = match body.payload() {
-        Ok(p) => p,
-        Err(_) => {
-            return Err(StatusCode::BAD_REQUEST);
+// Inserts a run of consecutive documents of a bulk write, recording the outcome under each document's operation index.
+// Documents get their `_id` up front, so the ids of the inserts that went through are known when others fail.
+// Returns whether every document was written.
+async fn insert_run(db: &Collection<Document>, run: Vec<(usize, Document)>, insert_opts: InsertOneOptions, ordered: bool, result: &mut BulkWriteResult, format: ExtJsonFormat) -> bool {
+    let (indexes, docs): (Vec<usize>, Vec<Document>) = run
+        .into_iter()
+        .map(|(index, doc)| match doc.contains_key("_id") {
+            true => (index, doc),
+            false => {
+                let mut with_id = doc! { "_id": ObjectId::new() };
+                with_id.extend(doc);
+                (index, with_id)
+            }
+        })
+        .unzip();
+    let ids: Vec<Bson> = docs.iter().map(|doc| doc.get("_id").cloned().unwrap_or(Bson::Null)).collect();
+
+    let mut insert_many_opts = InsertManyOptions::default();
+    insert_many_opts.bypass_document_validation = insert_opts.bypass_document_validation;
+    insert_many_opts.write_concern = insert_opts.write_concern;
+    insert_many_opts.ordered = Some(ordered);
+
+    let err = match db.insert_many(docs, insert_many_opts).await {
+        Ok(_) => {
+            for (index, id) in indexes.into_iter().zip(ids) {
+                result.inserted_count += 1;
+                result.inserted_ids.insert(index, bson_as_json(id, format));
+            }
+            return true;
+        },
+        Err(err) => err
+    };
+
+    let failure = match err.kind.as_ref() {
+        ErrorKind::BulkWrite(failure) => failure,
+        _ => {
+            // Nothing tells which of the documents were written
+            let (_, body) = ApiError::from(err).into_parts();
+            result.write_errors.push(json!({ "index": indexes[0], "errorCode": body["errorCode"], "message": body["error"], "details": body.get("details") }));
+            return false;
         }
     };
 
-    match db.aggregate(pipeline, body.opts()).await {
-        Ok(cursor) => {
-            if let Ok(res) = docs_as_json(cursor).await {
-                Ok(Json(res))
-            } else {
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    let write_errors = failure.write_errors.as_deref().unwrap_or_default();
+    for e in write_errors {
+        result.write_errors.push(json!({ "index": indexes[e.index], "code": e.code, "codeName": e.code_name, "message": e.message, "errInfo": e.details }));
+    }
+
+    // An ordered insert stops at its first error, an unordered one only skips the failed documents
+    let failed: HashSet<usize> = write_errors.iter().map(|e| e.index).collect();
+    let first_failed = failed.iter().min().copied().unwrap_or(indexes.len());
+
+    for (i, (index, id)) in indexes.iter().zip(ids).enumerate() {
+        if failed.contains(&i) || (ordered && i > first_failed) {
+            continue;
+        }
+
+        match &failure.write_concern_error {
+            // The write itself was applied, only its acknowledgement is missing
+            Some(e) => result.write_concern_errors.push(json!({ "index": index, "code": e.code, "codeName": e.code_name, "message": e.message, "errInfo": e.details })),
+            None => {
+                result.inserted_count += 1;
+                result.inserted_ids.insert(*index, bson_as_json(id, format));
             }
-        },
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+
+    failed.is_empty()
+}
+
+fn record_update(result: &mut BulkWriteResult, index: usize, res: UpdateResult, format: ExtJsonFormat) {
+    result.matched_count += res.matched_count;
+    result.modified_count += res.modified_count;
+
+    if let Some(upserted_id) = res.upserted_id {
+        result.upserted_count += 1;
+        result.upserted_ids.insert(index, bson_as_json(upserted_id, format));
+    }
+}
+
+/// Runs every operation in one transaction on the request's data source, committing only if all of them succeed.
+async fn transaction(Extension(state): Extension<Mongo>, Extension(format): Extension<ExtJsonFormat>, Payload(body): Payload<TransactionRequest>) -> Result<Json<Value>, ApiError> {
+    let client = state.client(body.data_source())?;
+
+    let ops = body.operations()
+        .iter()
+        .enumerate()
+        .map(|(i, op)| transaction_operation(&state, body.data_source(), op).map_err(|e| e.nested(&format!("operations.{}", i))))
+        .collect::<Result<Vec<(Collection<Document>, Operation)>, ApiError>>()?;
+
+    let mut session = client.start_session(None).await?;
+    let results = run_transaction(&mut session, &ops, body.opts(), format).await?;
+
+    Ok(Json(json!({ "results": results })))
+}
+
+// Transactions are bound to a single client, so operations cannot name a different data source
+fn transaction_operation(state: &Mongo, data_source: Option<&str>, op: &TransactionOperation) -> Result<(Collection<Document>, Operation), ApiError> {
+    let default_source = state.default_source.as_str();
+    if op.data_source().unwrap_or(default_source) != data_source.unwrap_or(default_source) {
+        return Err(ApiError::invalid_field("dataSource", "must match the transaction's dataSource"));
     }
+
+    let db = state.database(data_source, op.database())?;
+    let operation = op.operation()?;
+    let rules = state.query_policy.rules(db.name(), Some(op.coll()));
+
+    rules.check(&operation)?;
+
+    if let Operation::Aggregate(pipeline, _) = &operation {
+        state.check_pipeline(&rules, db.name(), pipeline)?;
+    }
+
+    Ok((db.collection(op.coll()), operation))
+}
+
+
+/// Starts a transaction that later requests join by sending the returned token in the `sessionToken` header.
+async fn start_session(Extension(state): Extension<Mongo>, Caller(caller): Caller, Payload(body): Payload<StartSessionRequest>) -> Result<Json<Value>, ApiError> {
+    let client = state.client(body.data_source())?;
+    let data_source = body.data_source().unwrap_or(&state.default_source).to_string();
+
+    let mut session = client.start_session(None).await?;
+    session.start_transaction(body.opts()).await?;
+
+    let token = state.sessions.insert(&caller, TransactionSession::new(session, data_source))?;
+
+    Ok(Json(json!({ "sessionToken": token })))
+}
+
+async fn commit_session(Extension(state): Extension<Mongo>, Caller(caller): Caller, SessionToken(token): SessionToken) -> Result<Json<Value>, ApiError> {
+    state.sessions.get(&token, &caller)?.commit().await?;
+    state.sessions.remove(&token);
+
+    Ok(Json(json!({ "committed": true })))
+}
+
+async fn abort_session(Extension(state): Extension<Mongo>, Caller(caller): Caller, SessionToken(token): SessionToken) -> Result<Json<Value>, ApiError> {
+    state.sessions.get(&token, &caller)?.abort().await?;
+    state.sessions.remove(&token);
+
+    Ok(Json(json!({ "aborted": true })))
 }
@@ -0,0 +1,215 @@
+use std::{collections::{HashMap, HashSet}, env};
+
+use axum::{Extension, extract::ws::{Message, WebSocket, WebSocketUpgrade}, response::Response};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use hyper::StatusCode;
+use mongodb::{Collection, bson::{doc, Bson, Document}, change_stream::event::OperationType, options::FindOneOptions};
+use serde_json::{json, Value};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{state::state::Mongo, middleware::{auth::BEARER_PROTOCOL, headers::ExtJsonFormat}, types::{errors::ApiError, mongo::{requests::{find::FindRequest, subscription::SubscriptionMessage}, traits::requests::{FilterQuery, MongoRequest, Namespaced}}}, utils::mongo::{bson_as_json, doc_as_json}};
+
+// Messages queued for a slow client before its subscriptions stop reading their change streams
+const OUTGOING_BUFFER: usize = 64;
+
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 10;
+
+// The initial results are read into memory and their ids kept for as long as the subscription runs
+const MAX_INITIAL_RESULTS: i64 = 1000;
+
+/// Number of live queries one connection may run at once, each holding a change stream open.
+/// Read from `MAX_SUBSCRIPTIONS_PER_CONNECTION`.
+#[derive(Debug, Clone, Copy)]
+pub struct SubscriptionLimit(usize);
+
+impl SubscriptionLimit {
+    pub fn from_env() -> Self {
+        let limit = env::var("MAX_SUBSCRIPTIONS_PER_CONNECTION")
+            .map(|val| val.parse::<usize>().expect("Error: Failed to parse MAX_SUBSCRIPTIONS_PER_CONNECTION from environment"))
+            .unwrap_or(DEFAULT_MAX_SUBSCRIPTIONS);
+
+        SubscriptionLimit(limit)
+    }
+}
+
+/// Live queries over a WebSocket. Each subscription first receives the `results` of its query, then a
+/// `change` whenever a document enters (`insert`), changes within (`update`) or leaves (`delete`) the result set.
+/// `sort`, `limit` and `skip` only apply to the initial results, which may hold at most 1000 documents.
+/// `stream`, `cursor`, `pageSize` and `pageToken` are not supported.
+pub async fn ws(upgrade: WebSocketUpgrade, Extension(state): Extension<Mongo>, Extension(limit): Extension<SubscriptionLimit>) -> Response {
+    // Browsers close the connection unless the server selects one of the subprotocols they offered
+    upgrade.protocols([BEARER_PROTOCOL]).on_upgrade(move |socket| connection(socket, state, limit))
+}
+
+async fn connection(socket: WebSocket, state: Mongo, SubscriptionLimit(limit): SubscriptionLimit) {
+    let (mut sink, mut incoming) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Value>(OUTGOING_BUFFER);
+
+    let writer = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if sink.send(Message::Text(message.to_string())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut subscriptions: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    while let Some(Ok(message)) = incoming.next().await {
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue
+        };
+
+        let reply = match serde_json::from_str::<SubscriptionMessage>(&text) {
+            Ok(SubscriptionMessage::Subscribe { id, query }) => {
+                // Subscriptions that ended on an error or an invalidation no longer count
+                subscriptions.retain(|_, task| !task.is_finished());
+
+                if subscriptions.contains_key(&id) {
+                    Some(error_message(Some(&id), ApiError::BadRequest(format!("Subscription '{}' already exists", id))))
+                } else if subscriptions.len() >= limit {
+                    Some(error_message(Some(&id), ApiError::TooManyRequests(format!("A connection may run at most {} subscriptions", limit))))
+                } else {
+                    let task = tokio::spawn(subscription(state.clone(), id.clone(), *query, tx.clone()));
+                    subscriptions.insert(id, task);
+                    None
+                }
+            },
+            Ok(SubscriptionMessage::Unsubscribe { id }) => {
+                match subscriptions.remove(&id) {
+                    Some(task) => {
+                        task.abort();
+                        Some(json!({ "type": "unsubscribed", "id": id }))
+                    },
+                    None => Some(error_message(Some(&id), ApiError::NotFound(format!("Unknown subscription '{}'", id))))
+                }
+            },
+            Err(e) => Some(error_message(None, ApiError::InvalidBody { status: StatusCode::BAD_REQUEST, message: e.to_string() }))
+        };
+
+        if let Some(reply) = reply {
+            if tx.send(reply).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    for task in subscriptions.into_values() {
+        task.abort();
+    }
+    writer.abort();
+}
+
+async fn subscription(state: Mongo, id: String, query: FindRequest, tx: mpsc::Sender<Value>) {
+    if let Err(err) = live_query(&state, &id, query, &tx).await {
+        let _ = tx.send(error_message(Some(&id), err)).await;
+    }
+}
+
+async fn live_query(state: &Mongo, id: &str, query: FindRequest, tx: &mpsc::Sender<Value>) -> Result<(), ApiError> {
+    let unsupported = [("stream", query.stream()), ("cursor", query.cursor()), ("pageSize", query.page_size().is_some()), ("pageToken", query.page_token().is_some())];
+    if let Some((field, _)) = unsupported.iter().find(|(_, set)| *set) {
+        return Err(ApiError::invalid_field(format!("query.{}", field), "is not supported by live queries"));
+    }
+
+    let coll: Collection<Document> = state.database(query.data_source(), query.database())?.collection(query.coll());
+    let filter = query.filter().transpose()?.unwrap_or_default();
+    let rules = state.query_policy.rules(coll.namespace().db.as_str(), Some(query.coll()));
+    rules.check_filter(&filter)?;
+    let mut find_opts = query.opts()?;
+    rules.check(&find_opts)?;
+
+    let capped = match find_opts.limit {
+        Some(limit) if limit.unsigned_abs() > MAX_INITIAL_RESULTS as u64 => {
+            return Err(ApiError::invalid_field("query.limit", format!("must be at most {}", MAX_INITIAL_RESULTS)));
+        },
+        Some(_) => false,
+        None => {
+            // One extra document tells whether the query matches more than may be read
+            find_opts.limit = Some(MAX_INITIAL_RESULTS + 1);
+            true
+        }
+    };
+
+    // Opened before the initial query so that no change between the two is missed
+    let pipeline = [doc! { "$match": { "operationType": { "$in": ["insert", "update", "replace", "delete", "drop", "rename", "dropDatabase", "invalidate"] } } }];
+    let mut changes = coll.watch(pipeline, None).await?;
+
+    let docs: Vec<Document> = coll.find(filter.clone(), find_opts.clone()).await?.try_collect().await?;
+    if capped && docs.len() as i64 > MAX_INITIAL_RESULTS {
+        return Err(ApiError::invalid_field("query.limit", format!("must be set when the query matches more than {} documents", MAX_INITIAL_RESULTS)));
+    }
+    let mut tracked: HashSet<String> = docs.iter().filter_map(|doc| doc.get("_id")).map(id_key).collect();
+
+    let results: Vec<Value> = docs.into_iter().map(|doc| doc_as_json(doc, ExtJsonFormat::Relaxed)).collect();
+    if tx.send(json!({ "type": "results", "id": id, "documents": results })).await.is_err() {
+        return Ok(());
+    }
+
+    let lookup_opts = FindOneOptions::builder().projection(find_opts.projection).build();
+
+    while let Some(change) = changes.next().await.transpose()? {
+        let key = match change.document_key.as_ref().and_then(|key| key.get("_id")) {
+            Some(key) => key.clone(),
+            None => {
+                if matches!(change.operation_type, OperationType::Insert | OperationType::Update | OperationType::Replace | OperationType::Delete) {
+                    continue;
+                }
+
+                // The collection is gone, so the subscription cannot continue
+                let _ = tx.send(json!({ "type": "invalidated", "id": id })).await;
+                return Ok(());
+            }
+        };
+
+        let was_tracked = tracked.contains(&id_key(&key));
+
+        // Looked up again rather than matched against the event, so the filter is evaluated by the server
+        let current = match change.operation_type {
+            OperationType::Delete => None,
+            _ => coll.find_one(doc! { "$and": [{ "_id": key.clone() }, filter.clone()] }, lookup_opts.clone()).await?
+        };
+
+        let message = match (current, was_tracked) {
+            (Some(doc), true) => change_message(id, "update", key, Some(doc)),
+            (Some(doc), false) => {
+                tracked.insert(id_key(&key));
+                change_message(id, "insert", key, Some(doc))
+            },
+            (None, true) => {
+                tracked.remove(&id_key(&key));
+                change_message(id, "delete", key, None)
+            },
+            (None, false) => continue
+        };
+
+        if tx.send(message).await.is_err() {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+fn change_message(id: &str, operation_type: &str, key: Bson, doc: Option<Document>) -> Value {
+    json!({
+        "type": "change",
+        "id": id,
+        "operationType": operation_type,
+        "documentKey": { "_id": bson_as_json(key, ExtJsonFormat::Relaxed) },
+        "document": doc.map(|doc| doc_as_json(doc, ExtJsonFormat::Relaxed))
+    })
+}
+
+fn error_message(id: Option<&str>, err: ApiError) -> Value {
+    let (_, body) = err.into_parts();
+
+    json!({ "type": "error", "id": id, "error": body })
+}
+
+// Bson is not hashable, so ids are tracked by their Extended JSON form
+fn id_key(id: &Bson) -> String {
+    id.clone().into_relaxed_extjson().to_string()
+}
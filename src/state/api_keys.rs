@@ -0,0 +1,131 @@
+use std::{collections::HashMap, env, fs, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use mongodb::{bson::{self, doc, Bson, DateTime}, Client, Collection};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{types::{errors::ApiError, principal::{AuthScheme, Principal}}, utils::token::hex};
+
+const DEFAULT_CACHE_SECS: u64 = 30;
+
+// Bounds the cache when it is flooded with unknown keys
+const MAX_CACHED_KEYS: usize = 10_000;
+
+/// A key as stored in `API_KEYS_FILE` or `API_KEYS_COLLECTION`. Only the hex encoded SHA-256 of the key is kept.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiKeyRecord {
+    name: String,
+    key_hash: String,
+    // Required of every key for auditing, but not checked
+    #[allow(dead_code)]
+    created_at: DateTime,
+    expires_at: Option<DateTime>,
+    #[serde(default)]
+    revoked: bool
+}
+
+#[derive(Debug, Clone)]
+enum KeyStore {
+    File(Arc<HashMap<String, ApiKeyRecord>>),
+    Collection(Collection<ApiKeyRecord>)
+}
+
+type CachedKey = (Option<ApiKeyRecord>, Instant);
+
+/// API keys checked by `auth_mw`. Keys are read once from `API_KEYS_FILE`, or looked up in `API_KEYS_COLLECTION`
+/// and cached for `API_KEY_CACHE_SECS`, so revoking a stored key takes up to that long. Without either, requests
+/// are not authenticated.
+#[derive(Debug, Clone)]
+pub struct ApiKeys {
+    store: Option<KeyStore>,
+    cache: Arc<Mutex<HashMap<String, CachedKey>>>,
+    cache_ttl: Duration
+}
+
+impl ApiKeys {
+    /// Reads the key store from the environment, `API_KEYS_COLLECTION` is a `database.collection` on `client`.
+    pub fn from_env(client: &Client) -> Self {
+        let store = match (env::var("API_KEYS_FILE"), env::var("API_KEYS_COLLECTION")) {
+            (Ok(path), _) => {
+                let contents = fs::read_to_string(&path).expect("Error: Failed to read API_KEYS_FILE");
+                let keys: Vec<Value> = serde_json::from_str(&contents).expect("Error: Failed to parse API_KEYS_FILE");
+
+                // Dates are Extended JSON, e.g. {"$date": "2024-01-01T00:00:00Z"}, as in request bodies
+                let keys = keys
+                    .into_iter()
+                    .map(|key| Bson::try_from(key).ok().and_then(|key| bson::from_bson::<ApiKeyRecord>(key).ok()))
+                    .map(|key| key.expect("Error: Failed to parse a key of API_KEYS_FILE"))
+                    .map(|key| (key.key_hash.to_lowercase(), key))
+                    .collect();
+
+                Some(KeyStore::File(Arc::new(keys)))
+            },
+            (_, Ok(namespace)) => {
+                let (db, coll) = namespace.split_once('.').expect("Error: API_KEYS_COLLECTION must be database.collection");
+                Some(KeyStore::Collection(client.database(db).collection(coll)))
+            },
+            _ => None
+        };
+
+        let cache_secs = env::var("API_KEY_CACHE_SECS")
+            .map(|val| val.parse::<u64>().expect("Error: Failed to parse API_KEY_CACHE_SECS from environment"))
+            .unwrap_or(DEFAULT_CACHE_SECS);
+
+        ApiKeys { store, cache: Arc::new(Mutex::new(HashMap::new())), cache_ttl: Duration::from_secs(cache_secs) }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// Database holding `API_KEYS_COLLECTION`, if keys are stored in one.
+    pub fn database(&self) -> Option<String> {
+        match &self.store {
+            Some(KeyStore::Collection(keys)) => Some(keys.namespace().db),
+            _ => None
+        }
+    }
+
+    /// Resolves the principal a key belongs to, a `401` when the key is unknown, revoked or expired.
+    pub async fn authenticate(&self, key: &str) -> Result<Principal, ApiError> {
+        let hash = hex(&Sha256::digest(key.as_bytes()));
+
+        match self.lookup(&hash).await? {
+            Some(record) if record.revoked => Err(ApiError::Unauthorized(format!("API key '{}' has been revoked", record.name))),
+            Some(record) if record.expires_at.map(|expires_at| expires_at <= DateTime::now()).unwrap_or(false) => {
+                Err(ApiError::Unauthorized(format!("API key '{}' has expired", record.name)))
+            },
+            Some(record) => Ok(Principal::new(AuthScheme::ApiKey, record.name)),
+            None => Err(ApiError::Unauthorized("Invalid API key".to_string()))
+        }
+    }
+
+    // Records are cached rather than outcomes, so expiry is still checked on every request
+    async fn lookup(&self, hash: &str) -> Result<Option<ApiKeyRecord>, ApiError> {
+        let keys = match &self.store {
+            Some(KeyStore::File(keys)) => return Ok(keys.get(hash).cloned()),
+            Some(KeyStore::Collection(keys)) => keys,
+            None => return Ok(None)
+        };
+
+        if let Some((record, fetched_at)) = self.cache.lock().unwrap().get(hash) {
+            if fetched_at.elapsed() < self.cache_ttl {
+                return Ok(record.clone());
+            }
+        }
+
+        let record = keys.find_one(doc! { "keyHash": hash }, None).await?;
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED_KEYS {
+            cache.retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.cache_ttl);
+        }
+        if cache.len() < MAX_CACHED_KEYS {
+            cache.insert(hash.to_string(), (record.clone(), Instant::now()));
+        }
+
+        Ok(record)
+    }
+}
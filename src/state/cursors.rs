@@ -0,0 +1,157 @@
+use std::{collections::HashMap, env, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use futures::StreamExt;
+use mongodb::{bson::Document, Cursor};
+
+use crate::{types::errors::ApiError, utils::token::new_token};
+
+const DEFAULT_CURSOR_TIMEOUT_SECS: u64 = 600;
+const DEFAULT_MAX_OPEN_CURSORS: usize = 1000;
+
+// Same as the server's default first batch
+const DEFAULT_BATCH_SIZE: usize = 101;
+const MAX_BATCH_SIZE: usize = 10_000;
+
+/// A cursor left open by `/find` or `/aggregate`, continued through `/getMore`.
+type SharedCursor = Arc<tokio::sync::Mutex<Cursor<Document>>>;
+
+#[derive(Debug)]
+struct CursorEntry {
+    cursor: SharedCursor,
+    caller: String,
+    last_used: Instant
+}
+
+/// Open cursors keyed by their opaque id. Cursors unused for `CURSOR_TIMEOUT_SECS` are dropped, which kills
+/// them on the server, and at most `MAX_OPEN_CURSORS` may be open at once.
+#[derive(Debug, Clone)]
+pub struct CursorRegistry {
+    entries: Arc<Mutex<HashMap<String, CursorEntry>>>,
+    timeout: Duration,
+    max_open: usize
+}
+
+impl CursorRegistry {
+    /// Reads the limits from the environment and starts the task that drops expired cursors.
+    pub fn from_env() -> Self {
+        let timeout = env::var("CURSOR_TIMEOUT_SECS")
+            .map(|val| val.parse::<u64>().expect("Error: Failed to parse CURSOR_TIMEOUT_SECS from environment"))
+            .unwrap_or(DEFAULT_CURSOR_TIMEOUT_SECS);
+
+        let max_open = env::var("MAX_OPEN_CURSORS")
+            .map(|val| val.parse::<usize>().expect("Error: Failed to parse MAX_OPEN_CURSORS from environment"))
+            .unwrap_or(DEFAULT_MAX_OPEN_CURSORS);
+
+        let registry = CursorRegistry {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            timeout: Duration::from_secs(timeout),
+            max_open
+        };
+
+        let reaper = registry.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval((reaper.timeout / 2).max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                reaper.entries.lock().unwrap().retain(|_, entry| entry.last_used.elapsed() < reaper.timeout);
+            }
+        });
+
+        registry
+    }
+
+    /// Reads the first batch. When the cursor has more documents it is registered for `caller` and its id returned.
+    pub async fn first_batch(&self, caller: &str, mut cursor: Cursor<Document>, batch_size: usize) -> Result<(Option<String>, Vec<Document>), ApiError> {
+        let (docs, exhausted) = next_batch(&mut cursor, batch_size).await?;
+
+        if exhausted {
+            return Ok((None, docs));
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.max_open {
+            return Err(ApiError::TooManyRequests(format!("At most {} cursors may be open at once", self.max_open)));
+        }
+
+        let id = new_token();
+        let cursor = Arc::new(tokio::sync::Mutex::new(cursor));
+        entries.insert(id.clone(), CursorEntry { cursor, caller: caller.to_string(), last_used: Instant::now() });
+
+        Ok((Some(id), docs))
+    }
+
+    /// Reads the next batch of a cursor owned by `caller`, releasing the cursor once it is exhausted.
+    pub async fn get_more(&self, id: &str, caller: &str, batch_size: usize) -> Result<(Option<String>, Vec<Document>), ApiError> {
+        let cursor = self.get(id, caller)?;
+        let (docs, exhausted) = next_batch(&mut *cursor.lock().await, batch_size).await?;
+
+        if exhausted {
+            self.entries.lock().unwrap().remove(id);
+            return Ok((None, docs));
+        }
+
+        Ok((Some(id.to_string()), docs))
+    }
+
+    /// Releases a cursor owned by `caller`, returning whether it was open.
+    pub fn kill(&self, id: &str, caller: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(id) {
+            Some(entry) if entry.caller == caller => entries.remove(id).is_some(),
+            _ => false
+        }
+    }
+
+    fn get(&self, id: &str, caller: &str) -> Result<SharedCursor, ApiError> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get_mut(id) {
+            Some(entry) if entry.caller == caller && entry.last_used.elapsed() < self.timeout => {
+                entry.last_used = Instant::now();
+                Ok(entry.cursor.clone())
+            },
+            _ => Err(ApiError::NotFound(format!("Unknown or expired cursor '{}'", id)))
+        }
+    }
+}
+
+/// The `batchSize` of a cursor request, where 0 means the default as it does for the server. Larger batches than
+/// `MAX_BATCH_SIZE` are a `400`, since a whole batch is held in memory.
+pub fn batch_size(requested: Option<u32>) -> Result<usize, ApiError> {
+    match requested.map(|size| size as usize) {
+        None | Some(0) => Ok(DEFAULT_BATCH_SIZE),
+        Some(size) if size > MAX_BATCH_SIZE => Err(ApiError::invalid_field("batchSize", format!("must be at most {}", MAX_BATCH_SIZE))),
+        Some(size) => Ok(size)
+    }
+}
+
+// Exhausted when the cursor ran out before filling the batch
+async fn next_batch(cursor: &mut Cursor<Document>, batch_size: usize) -> Result<(Vec<Document>, bool), ApiError> {
+    let mut docs = Vec::new();
+
+    while docs.len() < batch_size {
+        match cursor.next().await {
+            Some(doc) => docs.push(doc?),
+            None => return Ok((docs, true))
+        }
+    }
+
+    Ok((docs, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_limits_batch_size() {
+        assert_eq!(batch_size(None).unwrap(), DEFAULT_BATCH_SIZE);
+        assert_eq!(batch_size(Some(0)).unwrap(), DEFAULT_BATCH_SIZE);
+        assert_eq!(batch_size(Some(500)).unwrap(), 500);
+        assert_eq!(batch_size(Some(MAX_BATCH_SIZE as u32)).unwrap(), MAX_BATCH_SIZE);
+        assert!(matches!(batch_size(Some(MAX_BATCH_SIZE as u32 + 1)), Err(ApiError::InvalidField { .. })));
+        assert!(matches!(batch_size(Some(u32::MAX)), Err(ApiError::InvalidField { .. })));
+    }
+}
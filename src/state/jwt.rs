@@ -0,0 +1,203 @@
+use std::{env, fmt, fs, sync::Arc};
+
+use jsonwebtoken::{decode, decode_header, errors::ErrorKind, jwk::{AlgorithmParameters, EllipticCurve, JwkSet}, Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+
+use crate::{types::{errors::ApiError, principal::{AuthScheme, Principal}}, utils::extract::Claims};
+
+const DEFAULT_LEEWAY_SECS: u64 = 60;
+
+#[derive(Clone)]
+struct VerificationKey {
+    kid: Option<String>,
+    alg: Algorithm,
+    key: DecodingKey
+}
+
+// Keeps secrets out of the state's Debug output
+impl fmt::Debug for VerificationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerificationKey").field("kid", &self.kid).field("alg", &self.alg).finish_non_exhaustive()
+    }
+}
+
+/// Verifies `Authorization: Bearer` tokens signed with HS256 (`JWT_HS256_SECRET`), RS256 (`JWT_RS256_PUBLIC_KEY_FILE`)
+/// or ES256 (`JWT_ES256_PUBLIC_KEY_FILE`), or with a key of the `JWT_JWKS_FILE` set named by the token's `kid`.
+/// Tokens must carry `exp` and `sub`, and match `JWT_AUDIENCE` and `JWT_ISSUER` when set. Without any key, bearer
+/// tokens are not accepted.
+#[derive(Debug, Clone)]
+pub struct JwtVerifier {
+    keys: Arc<Vec<VerificationKey>>,
+    audience: Option<Vec<String>>,
+    issuer: Option<Vec<String>>,
+    leeway: u64
+}
+
+impl JwtVerifier {
+    pub fn from_env() -> Self {
+        let mut keys = Vec::new();
+
+        if let Ok(secret) = env::var("JWT_HS256_SECRET") {
+            keys.push(VerificationKey { kid: None, alg: Algorithm::HS256, key: DecodingKey::from_secret(secret.as_bytes()) });
+        }
+
+        if let Ok(path) = env::var("JWT_RS256_PUBLIC_KEY_FILE") {
+            let pem = fs::read(&path).expect("Error: Failed to read JWT_RS256_PUBLIC_KEY_FILE");
+            let key = DecodingKey::from_rsa_pem(&pem).expect("Error: Failed to parse JWT_RS256_PUBLIC_KEY_FILE");
+            keys.push(VerificationKey { kid: None, alg: Algorithm::RS256, key });
+        }
+
+        if let Ok(path) = env::var("JWT_ES256_PUBLIC_KEY_FILE") {
+            let pem = fs::read(&path).expect("Error: Failed to read JWT_ES256_PUBLIC_KEY_FILE");
+            let key = DecodingKey::from_ec_pem(&pem).expect("Error: Failed to parse JWT_ES256_PUBLIC_KEY_FILE");
+            keys.push(VerificationKey { kid: None, alg: Algorithm::ES256, key });
+        }
+
+        if let Ok(path) = env::var("JWT_JWKS_FILE") {
+            let contents = fs::read_to_string(&path).expect("Error: Failed to read JWT_JWKS_FILE");
+            let jwks: JwkSet = serde_json::from_str(&contents).expect("Error: Failed to parse JWT_JWKS_FILE");
+
+            for jwk in &jwks.keys {
+                // Keys that cannot sign HS256, RS256 or ES256 tokens are skipped rather than rejected,
+                // so a shared set may also hold keys meant for other services
+                let alg = match &jwk.algorithm {
+                    AlgorithmParameters::OctetKey(_) => Algorithm::HS256,
+                    AlgorithmParameters::RSA(_) => Algorithm::RS256,
+                    AlgorithmParameters::EllipticCurve(params) if params.curve == EllipticCurve::P256 => Algorithm::ES256,
+                    _ => continue
+                };
+
+                if jwk.common.key_algorithm.map(|key_alg| key_alg.to_string() != format!("{:?}", alg)).unwrap_or(false) {
+                    continue;
+                }
+
+                let key = DecodingKey::from_jwk(jwk).expect("Error: Failed to parse a key of JWT_JWKS_FILE");
+                keys.push(VerificationKey { kid: jwk.common.key_id.clone(), alg, key });
+            }
+        }
+
+        let leeway = env::var("JWT_LEEWAY_SECS")
+            .map(|val| val.parse::<u64>().expect("Error: Failed to parse JWT_LEEWAY_SECS from environment"))
+            .unwrap_or(DEFAULT_LEEWAY_SECS);
+
+        JwtVerifier { keys: Arc::new(keys), audience: list_var("JWT_AUDIENCE"), issuer: list_var("JWT_ISSUER"), leeway }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Checks the token's signature and claims, a `401` when it is malformed, signed with an unknown key, expired,
+    /// not yet valid, or issued by or for someone else.
+    pub fn verify(&self, token: &str) -> Result<(Principal, Claims), ApiError> {
+        let header = decode_header(token).map_err(|_| ApiError::Unauthorized("Malformed bearer token".to_string()))?;
+        let key = self.key(header.alg, header.kid.as_deref())?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway;
+        validation.validate_nbf = true;
+
+        let mut required = vec!["exp", "sub"];
+        match &self.audience {
+            Some(audience) => {
+                validation.set_audience(audience);
+                required.push("aud");
+            },
+            None => validation.validate_aud = false
+        }
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(issuer);
+            required.push("iss");
+        }
+        validation.set_required_spec_claims(&required);
+
+        let claims = decode::<Map<String, Value>>(token, &key.key, &validation)
+            .map_err(|e| ApiError::Unauthorized(rejection_message(e.kind())))?
+            .claims;
+
+        match claims.get("sub") {
+            Some(Value::String(sub)) => Ok((Principal::new(AuthScheme::Bearer, sub.clone()), Claims(claims))),
+            _ => Err(ApiError::Unauthorized("Bearer token 'sub' claim must be a string".to_string()))
+        }
+    }
+
+    // A token naming a `kid` is checked against the JWKS key of that id, and otherwise, like tokens without one,
+    // against the single configured key of its algorithm
+    fn key(&self, alg: Algorithm, kid: Option<&str>) -> Result<&VerificationKey, ApiError> {
+        let found = kid
+            .and_then(|kid| self.keys.iter().find(|key| key.kid.as_deref() == Some(kid)))
+            .or_else(|| self.keys.iter().find(|key| key.kid.is_none() && key.alg == alg));
+
+        match found {
+            Some(key) if key.alg == alg => Ok(key),
+            Some(_) => Err(ApiError::Unauthorized(format!("Bearer token key does not sign {:?}", alg))),
+            None => Err(ApiError::Unauthorized("Bearer token is signed with an unknown key".to_string()))
+        }
+    }
+}
+
+fn rejection_message(kind: &ErrorKind) -> String {
+    match kind {
+        ErrorKind::ExpiredSignature => "Bearer token has expired".to_string(),
+        ErrorKind::ImmatureSignature => "Bearer token is not valid yet".to_string(),
+        ErrorKind::InvalidAudience => "Bearer token is not meant for this service".to_string(),
+        ErrorKind::InvalidIssuer => "Bearer token is issued by an untrusted issuer".to_string(),
+        ErrorKind::MissingRequiredClaim(claim) => format!("Bearer token has no '{}' claim", claim),
+        ErrorKind::InvalidSignature => "Bearer token signature is invalid".to_string(),
+        _ => "Invalid bearer token".to_string()
+    }
+}
+
+fn list_var(name: &str) -> Option<Vec<String>> {
+    env::var(name).ok().map(|val| val.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+
+    fn verifier(keys: Vec<VerificationKey>) -> JwtVerifier {
+        JwtVerifier { keys: Arc::new(keys), audience: None, issuer: None, leeway: 0 }
+    }
+
+    fn hs256(kid: Option<&str>, secret: &[u8]) -> VerificationKey {
+        VerificationKey { kid: kid.map(str::to_string), alg: Algorithm::HS256, key: DecodingKey::from_secret(secret) }
+    }
+
+    fn token(kid: Option<&str>, secret: &[u8]) -> String {
+        let header = Header { kid: kid.map(str::to_string), ..Header::new(Algorithm::HS256) };
+        encode(&header, &json!({ "sub": "alice", "exp": 4102444800u64 }), &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    #[test]
+    fn resolves_keys_by_kid_then_algorithm() {
+        let verifier = verifier(vec![hs256(None, b"single"), hs256(Some("a"), b"jwks")]);
+
+        assert_eq!(verifier.key(Algorithm::HS256, Some("a")).unwrap().kid.as_deref(), Some("a"));
+        assert_eq!(verifier.key(Algorithm::HS256, Some("unknown")).unwrap().kid, None);
+        assert_eq!(verifier.key(Algorithm::HS256, None).unwrap().kid, None);
+        assert!(verifier.key(Algorithm::RS256, None).is_err());
+        assert!(verifier.key(Algorithm::RS256, Some("a")).is_err());
+    }
+
+    #[test]
+    fn accepts_tokens_with_a_kid_against_a_single_key() {
+        let verifier = verifier(vec![hs256(None, b"single")]);
+
+        let (principal, _) = verifier.verify(&token(Some("idp-key-1"), b"single")).unwrap();
+        assert_eq!(principal.name(), "alice");
+        assert!(verifier.verify(&token(None, b"single")).is_ok());
+        assert!(verifier.verify(&token(Some("idp-key-1"), b"other")).is_err());
+    }
+
+    #[test]
+    fn checks_tokens_with_a_known_kid_only_against_that_key() {
+        let verifier = verifier(vec![hs256(None, b"single"), hs256(Some("a"), b"jwks")]);
+
+        assert!(verifier.verify(&token(Some("a"), b"jwks")).is_ok());
+        assert!(verifier.verify(&token(Some("a"), b"single")).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, env, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use mongodb::{bson::Document, ClientSession, Collection};
+use serde_json::Value;
+
+use crate::{middleware::headers::ExtJsonFormat, types::{errors::ApiError, mongo::operation::Operation}, utils::{session::{commit_transaction, run_operation}, token::new_token}};
+
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_MAX_SESSIONS_PER_CALLER: usize = 5;
+
+/// A transaction started through `/session/start`, shared by every request carrying its token.
+#[derive(Debug, Clone)]
+pub struct TransactionSession {
+    session: Arc<tokio::sync::Mutex<ClientSession>>,
+    data_source: String
+}
+
+impl TransactionSession {
+    pub fn new(session: ClientSession, data_source: String) -> Self {
+        TransactionSession { session: Arc::new(tokio::sync::Mutex::new(session)), data_source }
+    }
+
+    pub fn data_source(&self) -> &str {
+        &self.data_source
+    }
+
+    /// Runs the operation inside the transaction. Requests on the same token are serialized.
+    pub async fn run(&self, coll: &Collection<Document>, op: Operation, format: ExtJsonFormat) -> Result<Value, ApiError> {
+        let mut session = self.session.lock().await;
+
+        Ok(run_operation(coll, op, &mut session, format).await?)
+    }
+
+    pub async fn commit(&self) -> Result<(), ApiError> {
+        let mut session = self.session.lock().await;
+
+        Ok(commit_transaction(&mut session).await?)
+    }
+
+    pub async fn abort(&self) -> Result<(), ApiError> {
+        let mut session = self.session.lock().await;
+
+        Ok(session.abort_transaction().await?)
+    }
+}
+
+#[derive(Debug)]
+struct SessionEntry {
+    session: TransactionSession,
+    caller: String,
+    last_used: Instant
+}
+
+/// Open sessions keyed by their opaque token. Sessions idle for longer than `SESSION_IDLE_TIMEOUT_SECS`
+/// are aborted, and each caller may hold at most `MAX_SESSIONS_PER_CALLER` at once.
+#[derive(Debug, Clone)]
+pub struct SessionTable {
+    entries: Arc<Mutex<HashMap<String, SessionEntry>>>,
+    idle_timeout: Duration,
+    max_per_caller: usize
+}
+
+impl SessionTable {
+    /// Reads the limits from the environment and starts the task that aborts expired sessions.
+    pub fn from_env() -> Self {
+        let idle_timeout = env::var("SESSION_IDLE_TIMEOUT_SECS")
+            .map(|val| val.parse::<u64>().expect("Error: Failed to parse SESSION_IDLE_TIMEOUT_SECS from environment"))
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+
+        let max_per_caller = env::var("MAX_SESSIONS_PER_CALLER")
+            .map(|val| val.parse::<usize>().expect("Error: Failed to parse MAX_SESSIONS_PER_CALLER from environment"))
+            .unwrap_or(DEFAULT_MAX_SESSIONS_PER_CALLER);
+
+        let table = SessionTable {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout: Duration::from_secs(idle_timeout),
+            max_per_caller
+        };
+
+        let reaper = table.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval((reaper.idle_timeout / 2).max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                for session in reaper.expire() {
+                    let _ = session.abort().await;
+                }
+            }
+        });
+
+        table
+    }
+
+    /// Registers the session for `caller` and returns its token, or a `429` when the caller is at its limit.
+    pub fn insert(&self, caller: &str, session: TransactionSession) -> Result<String, ApiError> {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.values().filter(|entry| entry.caller == caller).count() >= self.max_per_caller {
+            return Err(ApiError::TooManyRequests(format!("At most {} sessions may be open at once", self.max_per_caller)));
+        }
+
+        let token = new_token();
+        entries.insert(token.clone(), SessionEntry { session, caller: caller.to_string(), last_used: Instant::now() });
+
+        Ok(token)
+    }
+
+    /// Looks up a live session owned by `caller` and resets its idle timer.
+    pub fn get(&self, token: &str, caller: &str) -> Result<TransactionSession, ApiError> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get_mut(token) {
+            Some(entry) if entry.caller == caller && entry.last_used.elapsed() < self.idle_timeout => {
+                entry.last_used = Instant::now();
+                Ok(entry.session.clone())
+            },
+            _ => Err(unknown_session())
+        }
+    }
+
+    /// Forgets a session once it has been committed or aborted. Until then it stays registered, so that a failed
+    /// commit can be retried or aborted by the client, or is otherwise aborted when it idles out.
+    pub fn remove(&self, token: &str) {
+        self.entries.lock().unwrap().remove(token);
+    }
+
+    fn expire(&self) -> Vec<TransactionSession> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let expired: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| entry.last_used.elapsed() >= self.idle_timeout)
+            .map(|(token, _)| token.clone())
+            .collect();
+
+        expired.iter().filter_map(|token| entries.remove(token)).map(|entry| entry.session).collect()
+    }
+}
+
+// Tokens of other callers are reported the same way as unknown ones so they cannot be probed
+fn unknown_session() -> ApiError {
+    ApiError::NotFound("Unknown or expired session".to_string())
+}
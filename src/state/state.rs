@@ -1,6 +1,15 @@
-use std::env;
+use std::{collections::HashMap, env, fs, time::Duration};
 
-use mongodb::{options::ClientOptions, Client};
+use mongodb::{options::ClientOptions, bson::Document, Client};
+use serde::Deserialize;
+
+use crate::{state::{api_keys::ApiKeys, jwt::JwtVerifier, cursors::CursorRegistry, sessions::SessionTable}, types::errors::ApiError, utils::{pagination::PageSigner, query_policy::{QueryPolicy, QueryRules}}};
+
+// Databases that are never reachable through the API, regardless of the allowlist
+const RESERVED_DBS: [&str; 3] = ["admin", "local", "config"];
+
+// Name given to the MONGODB_URI client when no DATA_SOURCES_FILE is configured
+const DEFAULT_DATA_SOURCE: &str = "default";
 
 pub struct AppState {
     pub db: mongodb::Database
@@ -12,19 +21,164 @@ impl AppState {
     }
 }
 
+/// A single entry of the `DATA_SOURCES_FILE` JSON array.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSourceConfig {
+    name: String,
+    uri: String,
+    max_pool_size: Option<u32>,
+    min_pool_size: Option<u32>,
+    max_idle_time_ms: Option<u64>,
+    connect_timeout_ms: Option<u64>,
+    app_name: Option<String>
+}
+
+impl DataSourceConfig {
+    async fn client(&self) -> Client {
+        let mut client_options = ClientOptions::parse(&self.uri).await
+            .unwrap_or_else(|_| panic!("Error: Failed to parse MongoDB client options for data source {}", self.name));
+
+        if let Some(max_pool_size) = self.max_pool_size {
+            client_options.max_pool_size = Some(max_pool_size);
+        }
+
+        if let Some(min_pool_size) = self.min_pool_size {
+            client_options.min_pool_size = Some(min_pool_size);
+        }
+
+        if let Some(max_idle_time_ms) = self.max_idle_time_ms {
+            client_options.max_idle_time = Some(Duration::from_millis(max_idle_time_ms));
+        }
+
+        if let Some(connect_timeout_ms) = self.connect_timeout_ms {
+            client_options.connect_timeout = Some(Duration::from_millis(connect_timeout_ms));
+        }
+
+        if let Some(app_name) = &self.app_name {
+            client_options.app_name = Some(app_name.clone());
+        }
+
+        Client::with_options(client_options)
+            .unwrap_or_else(|_| panic!("Error: Failed to initialize MongoDB client for data source {}", self.name))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Mongo {
-    pub db: mongodb::Database
+    pub clients: HashMap<String, Client>,
+    pub default_source: String,
+    pub default_db: String,
+    pub allowed_dbs: Vec<String>,
+    pub sessions: SessionTable,
+    pub cursors: CursorRegistry,
+    pub page_signer: PageSigner,
+    pub query_policy: QueryPolicy,
+    pub api_keys: ApiKeys,
+    pub jwt: JwtVerifier
 }
 
 impl Mongo {
     pub async fn new() -> Self {
-        let mongo_uri = env::var("MONGODB_URI").expect("Error: Failed to get MONGO_URI from environment");
         let db_name = env::var("DB_NAME").expect("Error: Failed to get DB_NAME from environment");
-        let client_options = ClientOptions::parse(mongo_uri).await.expect("Error: Failed to parse MongoDB client options");
-        let client = Client::with_options(client_options).expect("Error: Failed to initialize MongoDB client with given options");
-        let db = client.database(&db_name);
 
-        Mongo { db }
+        let sources: Vec<DataSourceConfig> = match env::var("DATA_SOURCES_FILE") {
+            Ok(path) => {
+                let contents = fs::read_to_string(&path).expect("Error: Failed to read DATA_SOURCES_FILE");
+                serde_json::from_str(&contents).expect("Error: Failed to parse DATA_SOURCES_FILE")
+            },
+            Err(_) => {
+                let mongo_uri = env::var("MONGODB_URI").expect("Error: Failed to get MONGO_URI from environment");
+                vec![DataSourceConfig {
+                    name: DEFAULT_DATA_SOURCE.to_string(),
+                    uri: mongo_uri,
+                    max_pool_size: None,
+                    min_pool_size: None,
+                    max_idle_time_ms: None,
+                    connect_timeout_ms: None,
+                    app_name: None
+                }]
+            }
+        };
+
+        // DEFAULT_DATA_SOURCE picks the client used when a request has no dataSource, otherwise the first one listed
+        let default_source = env::var("DEFAULT_DATA_SOURCE")
+            .ok()
+            .or_else(|| sources.first().map(|source| source.name.clone()))
+            .expect("Error: No data sources configured");
+
+        let mut clients = HashMap::new();
+        for source in &sources {
+            if clients.insert(source.name.clone(), source.client().await).is_some() {
+                panic!("Error: Data source {} is configured more than once", source.name);
+            }
+        }
+
+        if !clients.contains_key(&default_source) {
+            panic!("Error: DEFAULT_DATA_SOURCE {} is not a configured data source", default_source);
+        }
+
+        // DB_ALLOWLIST is a comma separated list of database names, DB_NAME is always allowed
+        let mut allowed_dbs: Vec<String> = env::var("DB_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        if !allowed_dbs.contains(&db_name) {
+            allowed_dbs.push(db_name.clone());
+        }
+
+        let api_keys = ApiKeys::from_env(&clients[&default_source]);
+
+        // Otherwise any caller could read the key hashes, or mint and un-revoke keys, through the API itself
+        if let Some(keys_db) = api_keys.database() {
+            if allowed_dbs.contains(&keys_db) && !RESERVED_DBS.contains(&keys_db.as_str()) {
+                panic!("Error: API_KEYS_COLLECTION must not be in an allowlisted database, {} is reachable through the API", keys_db);
+            }
+        }
+
+        let jwt = JwtVerifier::from_env();
+
+        // A misspelled key setting must not silently leave the API open
+        let auth_disabled = env::var("AUTH_DISABLED")
+            .map(|val| val.parse::<bool>().expect("Error: Failed to parse AUTH_DISABLED from environment"))
+            .unwrap_or(false);
+
+        if !api_keys.enabled() && !jwt.enabled() && !auth_disabled {
+            panic!("Error: No API_KEYS_FILE, API_KEYS_COLLECTION or JWT key configured, set AUTH_DISABLED=true to serve /v1 without authentication");
+        }
+
+        Mongo { clients, default_source, default_db: db_name, allowed_dbs, sessions: SessionTable::from_env(), cursors: CursorRegistry::from_env(), page_signer: PageSigner::from_env(), query_policy: QueryPolicy::from_env(), api_keys, jwt }
+    }
+
+    /// Looks up a client by data source name, falling back to the default data source when none is given.
+    pub fn client(&self, data_source: Option<&str>) -> Result<&Client, ApiError> {
+        let data_source = data_source.unwrap_or(&self.default_source);
+
+        self.clients.get(data_source).ok_or_else(|| ApiError::UnknownDataSource(data_source.to_string()))
+    }
+
+    /// Resolves the requested database on the requested data source, falling back to `DB_NAME` when none is given.
+    /// Unknown data sources are a `400`, reserved or non-allowlisted databases a `403`.
+    pub fn database(&self, data_source: Option<&str>, name: Option<&str>) -> Result<mongodb::Database, ApiError> {
+        let client = self.client(data_source)?;
+        let name = name.unwrap_or(&self.default_db);
+
+        if !self.is_allowed(name) {
+            return Err(ApiError::ForbiddenDatabase(name.to_string()));
+        }
+
+        Ok(client.database(name))
+    }
+
+    /// Applies the pipeline policy of `rules` to a pipeline run against `db`, a `403` when it writes or reads where it may not.
+    pub fn check_pipeline(&self, rules: &QueryRules, db: &str, pipeline: &[Document]) -> Result<(), ApiError> {
+        rules.pipeline_policy().check(db, pipeline, &|name| self.is_allowed(name))
+    }
+
+    pub fn is_allowed(&self, db_name: &str) -> bool {
+        !RESERVED_DBS.contains(&db_name) && self.allowed_dbs.iter().any(|db| db == db_name)
     }
 }
\ No newline at end of file
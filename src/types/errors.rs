@@ -0,0 +1,192 @@
+use std::{fmt::Display, io};
+
+use axum::{response::{IntoResponse, Response}, Json};
+use hyper::StatusCode;
+use mongodb::error::{ErrorKind, GridFsErrorKind, WriteFailure};
+use serde_json::{json, Value};
+
+/// Error returned by every handler and middleware, rendered as
+/// `{"error": ..., "errorCode": ..., "details": ...}`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The body could not be read or deserialized into the request type
+    InvalidBody { status: StatusCode, message: String },
+    /// A field was present but could not be converted to BSON
+    InvalidField { field: String, message: String },
+    MissingField(String),
+    /// A filter or pipeline uses an operator or shape the query policy rejects
+    QueryPolicy { field: String, message: String },
+    UnsupportedMediaType(String),
+    UnknownDataSource(String),
+    ForbiddenDatabase(String),
+    Unauthorized(String),
+    Forbidden(String),
+    BadRequest(String),
+    NotFound(String),
+    TooManyRequests(String),
+    Mongo(mongodb::error::Error)
+}
+
+impl ApiError {
+    pub fn invalid_field(field: impl Into<String>, err: impl Display) -> Self {
+        ApiError::InvalidField { field: field.into(), message: err.to_string() }
+    }
+
+    pub fn missing_field(field: impl Into<String>) -> Self {
+        ApiError::MissingField(field.into())
+    }
+
+    pub fn query_policy(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ApiError::QueryPolicy { field: field.into(), message: message.into() }
+    }
+
+    /// Nests the reported field under `parent`, e.g. `filter` becomes `operations.2.filter`.
+    pub fn nested(self, parent: &str) -> Self {
+        match self {
+            ApiError::InvalidField { field, message } => ApiError::InvalidField { field: format!("{}.{}", parent, field), message },
+            ApiError::MissingField(field) => ApiError::MissingField(format!("{}.{}", parent, field)),
+            ApiError::QueryPolicy { field, message } => ApiError::QueryPolicy { field: format!("{}.{}", parent, field), message },
+            other => other
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for ApiError {
+    fn from(err: mongodb::error::Error) -> Self {
+        ApiError::Mongo(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.into_parts();
+
+        (status, Json(body)).into_response()
+    }
+}
+
+impl ApiError {
+    /// Status and JSON body of the error, for streaming responses that report failures in-band.
+    pub fn into_parts(self) -> (StatusCode, Value) {
+        let (status, error_code, message, details) = match self {
+            ApiError::InvalidBody { status, message } => (status, "InvalidBody", message, None),
+            ApiError::InvalidField { field, message } => (
+                StatusCode::BAD_REQUEST,
+                "InvalidParameter",
+                format!("Failed to convert '{}': {}", field, message),
+                Some(json!({ "field": field }))
+            ),
+            ApiError::MissingField(field) => (
+                StatusCode::BAD_REQUEST,
+                "MissingParameter",
+                format!("Missing required field '{}'", field),
+                Some(json!({ "field": field }))
+            ),
+            ApiError::QueryPolicy { field, message } => (
+                StatusCode::BAD_REQUEST,
+                "QueryPolicyViolation",
+                format!("{} at '{}'", message, field),
+                Some(json!({ "field": field }))
+            ),
+            ApiError::UnsupportedMediaType(content_type) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "UnsupportedMediaType",
+                format!("Unsupported content type '{}'", content_type),
+                None
+            ),
+            ApiError::UnknownDataSource(name) => (
+                StatusCode::BAD_REQUEST,
+                "UnknownDataSource",
+                format!("Unknown data source '{}'", name),
+                None
+            ),
+            ApiError::ForbiddenDatabase(name) => (
+                StatusCode::FORBIDDEN,
+                "ForbiddenDatabase",
+                format!("Access to database '{}' is not allowed", name),
+                None
+            ),
+            ApiError::Unauthorized(message) => (StatusCode::UNAUTHORIZED, "Unauthorized", message, None),
+            ApiError::Forbidden(message) => (StatusCode::FORBIDDEN, "Forbidden", message, None),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, "BadRequest", message, None),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, "NotFound", message, None),
+            ApiError::TooManyRequests(message) => (StatusCode::TOO_MANY_REQUESTS, "TooManyRequests", message, None),
+            ApiError::Mongo(err) => {
+                let (status, error_code, message, mut details) = mongo_error(&err);
+
+                // Session clients decide whether to retry from labels such as TransientTransactionError
+                if !err.labels().is_empty() {
+                    details.get_or_insert_with(|| json!({}))["errorLabels"] = json!(err.labels());
+                }
+
+                (status, error_code, message, details)
+            }
+        };
+
+        let mut body = json!({ "error": message, "errorCode": error_code });
+        if let Some(details) = details {
+            body["details"] = details;
+        }
+
+        (status, body)
+    }
+}
+
+fn mongo_error(err: &mongodb::error::Error) -> (StatusCode, &'static str, String, Option<Value>) {
+    match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => {
+            let (status, error_code) = server_code_status(e.code);
+            let details = json!({ "code": e.code, "codeName": e.code_name, "errInfo": e.details });
+            (status, error_code, e.message.clone(), Some(details))
+        },
+        ErrorKind::Write(WriteFailure::WriteConcernError(e)) => {
+            let details = json!({ "code": e.code, "codeName": e.code_name, "errInfo": e.details });
+            (StatusCode::GATEWAY_TIMEOUT, "WriteConcernError", e.message.clone(), Some(details))
+        },
+        ErrorKind::BulkWrite(failure) => {
+            let write_errors = failure.write_errors.as_deref().unwrap_or_default();
+
+            let (status, error_code, message) = match (write_errors.first(), &failure.write_concern_error) {
+                (Some(e), _) => {
+                    let (status, error_code) = server_code_status(e.code);
+                    (status, error_code, e.message.clone())
+                },
+                (None, Some(e)) => (StatusCode::GATEWAY_TIMEOUT, "WriteConcernError", e.message.clone()),
+                (None, None) => (StatusCode::INTERNAL_SERVER_ERROR, "MongoError", err.kind.to_string())
+            };
+
+            let write_errors: Vec<Value> = write_errors
+                .iter()
+                .map(|e| json!({ "index": e.index, "code": e.code, "codeName": e.code_name, "message": e.message, "errInfo": e.details }))
+                .collect();
+            let details = json!({ "writeErrors": write_errors, "writeConcernError": failure.write_concern_error });
+
+            (status, error_code, message, Some(details))
+        },
+        ErrorKind::Command(e) => {
+            let (status, error_code) = server_code_status(e.code);
+            (status, error_code, e.message.clone(), Some(json!({ "code": e.code, "codeName": e.code_name })))
+        },
+        ErrorKind::Authentication { .. } => (StatusCode::SERVICE_UNAVAILABLE, "AuthenticationFailed", err.kind.to_string(), None),
+        ErrorKind::ServerSelection { .. } => (StatusCode::GATEWAY_TIMEOUT, "ServerSelectionTimeout", err.kind.to_string(), None),
+        ErrorKind::Io(e) if e.kind() == io::ErrorKind::TimedOut => (StatusCode::GATEWAY_TIMEOUT, "Timeout", err.kind.to_string(), None),
+        // ErrorKind is non_exhaustive, so its tuple variants can only be matched by field outside the driver
+        ErrorKind::GridFs { 0: GridFsErrorKind::FileNotFound { .. }, .. } => (StatusCode::NOT_FOUND, "FileNotFound", err.kind.to_string(), None),
+        ErrorKind::InvalidArgument { .. } => (StatusCode::BAD_REQUEST, "InvalidArgument", err.kind.to_string(), None),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "MongoError", err.kind.to_string(), None)
+    }
+}
+
+// Maps server error codes, see https://github.com/mongodb/mongo/blob/master/src/mongo/base/error_codes.yml
+fn server_code_status(code: i32) -> (StatusCode, &'static str) {
+    match code {
+        11000 => (StatusCode::CONFLICT, "DuplicateKey"),
+        121 => (StatusCode::UNPROCESSABLE_ENTITY, "DocumentValidationFailure"),
+        50 => (StatusCode::GATEWAY_TIMEOUT, "MaxTimeMSExpired"),
+        64 => (StatusCode::GATEWAY_TIMEOUT, "WriteConcernFailed"),
+        13 => (StatusCode::FORBIDDEN, "Unauthorized"),
+        18 => (StatusCode::SERVICE_UNAVAILABLE, "AuthenticationFailed"),
+        2 | 9 | 14 | 40 | 52 | 66 | 72 => (StatusCode::BAD_REQUEST, "InvalidQuery"),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, "MongoError")
+    }
+}
@@ -0,0 +1,34 @@
+use mongodb::{options::{AggregateOptions, CountOptions, DeleteOptions, DistinctOptions, FindOneAndDeleteOptions, FindOneAndReplaceOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, InsertManyOptions, InsertOneOptions, ReplaceOptions, UpdateOptions}, bson::Document};
+
+/// A parsed data operation, ready to be run against a collection inside a session.
+#[derive(Clone)]
+pub enum Operation {
+    Find(Option<Document>, FindOptions),
+    FindOne(Option<Document>, FindOneOptions),
+    FindOneAndUpdate(Document, Document, FindOneAndUpdateOptions),
+    FindOneAndReplace(Document, Document, FindOneAndReplaceOptions),
+    FindOneAndDelete(Document, FindOneAndDeleteOptions),
+    InsertOne(Document, InsertOneOptions),
+    InsertMany(Vec<Document>, InsertManyOptions),
+    UpdateOne(Document, Document, UpdateOptions),
+    UpdateMany(Document, Document, UpdateOptions),
+    ReplaceOne(Document, Document, ReplaceOptions),
+    DeleteOne(Document, DeleteOptions),
+    DeleteMany(Document, DeleteOptions),
+    Aggregate(Vec<Document>, AggregateOptions),
+    CountDocuments(Option<Document>, CountOptions),
+    Distinct(String, Option<Document>, DistinctOptions),
+}
+
+impl Operation {
+    /// The filter selecting the documents the operation reads or writes, if it takes one.
+    pub fn filter(&self) -> Option<&Document> {
+        match self {
+            Operation::Find(filter, _) | Operation::FindOne(filter, _) | Operation::CountDocuments(filter, _) | Operation::Distinct(_, filter, _) => filter.as_ref(),
+            Operation::FindOneAndUpdate(filter, _, _) | Operation::FindOneAndReplace(filter, _, _) | Operation::FindOneAndDelete(filter, _) => Some(filter),
+            Operation::UpdateOne(filter, _, _) | Operation::UpdateMany(filter, _, _) | Operation::ReplaceOne(filter, _, _) => Some(filter),
+            Operation::DeleteOne(filter, _) | Operation::DeleteMany(filter, _) => Some(filter),
+            Operation::InsertOne(..) | Operation::InsertMany(..) | Operation::Aggregate(..) => None
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use mongodb::options::{HedgedReadOptions, ReadPreference, ReadPreferenceOptions, SelectionCriteria, TagSet};
+use serde::{Deserialize, Serialize};
+
+use crate::types::errors::ApiError;
+
+// Lowest maxStalenessSeconds servers accept
+const MIN_MAX_STALENESS_SECS: u64 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadPreferenceMode {
+    Primary,
+    PrimaryPreferred,
+    Secondary,
+    SecondaryPreferred,
+    Nearest
+}
+
+/// Read preference of a read request, either a bare mode such as `"secondaryPreferred"` or
+/// `{"mode": "secondary", "tagSets": [{"workload": "analytics"}], "maxStalenessSeconds": 120, "hedge": {"enabled": true}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged, expecting = "a read preference mode or {mode, tagSets, maxStalenessSeconds, hedge}")]
+pub enum ReadPreferenceRequest {
+    Mode(ReadPreferenceMode),
+    Options(ReadPreferenceSpec)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReadPreferenceSpec {
+    mode: ReadPreferenceMode,
+    tag_sets: Option<Vec<TagSet>>,
+    max_staleness_seconds: Option<u64>,
+    hedge: Option<HedgedReadOptions>
+}
+
+impl ReadPreferenceRequest {
+    pub fn selection_criteria(&self) -> Result<SelectionCriteria, ApiError> {
+        let (mode, options) = match self {
+            ReadPreferenceRequest::Mode(mode) => (*mode, ReadPreferenceOptions::default()),
+            ReadPreferenceRequest::Options(spec) => (spec.mode, spec.options()?)
+        };
+
+        let read_preference = match mode {
+            ReadPreferenceMode::Primary if options != ReadPreferenceOptions::default() => {
+                return Err(ApiError::invalid_field("readPreference", "primary does not take tagSets, maxStalenessSeconds or hedge"));
+            },
+            ReadPreferenceMode::Primary => ReadPreference::Primary,
+            ReadPreferenceMode::PrimaryPreferred => ReadPreference::PrimaryPreferred { options },
+            ReadPreferenceMode::Secondary => ReadPreference::Secondary { options },
+            ReadPreferenceMode::SecondaryPreferred => ReadPreference::SecondaryPreferred { options },
+            ReadPreferenceMode::Nearest => ReadPreference::Nearest { options }
+        };
+
+        Ok(SelectionCriteria::ReadPreference(read_preference))
+    }
+}
+
+impl ReadPreferenceSpec {
+    fn options(&self) -> Result<ReadPreferenceOptions, ApiError> {
+        if let Some(secs) = self.max_staleness_seconds {
+            if secs < MIN_MAX_STALENESS_SECS {
+                return Err(ApiError::invalid_field("readPreference.maxStalenessSeconds", format!("must be at least {}", MIN_MAX_STALENESS_SECS)));
+            }
+        }
+
+        Ok(ReadPreferenceOptions::builder()
+            .tag_sets(self.tag_sets.clone())
+            .max_staleness(self.max_staleness_seconds.map(Duration::from_secs))
+            .hedge(self.hedge.clone())
+            .build())
+    }
+}
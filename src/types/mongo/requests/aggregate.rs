@@ -1,8 +1,10 @@
-use mongodb::{options::{WriteConcern, ReadConcern, AggregateOptions}, bson::Document};
+use std::time::Duration;
+
+use mongodb::{options::{WriteConcern, ReadConcern, AggregateOptions, Collation, Hint}, bson::Document};
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 
-use crate::{types::mongo::traits::requests::{MongoRequest, DocumentPayload}, utils::mongo::parse_docs};
+use crate::{types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::{MongoRequest, DocumentPayload, Namespaced}}}, utils::mongo::{parse_docs, parse_field, parse_value}};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,7 +16,34 @@ pub struct AggregateRequest {
     bypass_document_validation: Option<bool>,
     write_concern: Option<WriteConcern>,
     batch_size: Option<u32>,
-    read_concern: Option<ReadConcern>
+    read_concern: Option<ReadConcern>,
+    stream: Option<bool>,
+    cursor: Option<bool>,
+    allow_disk_use: Option<bool>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    hint: Option<Hint>,
+    collation: Option<Collation>,
+    #[serde(rename = "let")]
+    let_vars: Option<Value>,
+    comment: Option<Value>,
+    read_preference: Option<ReadPreferenceRequest>,
+}
+
+impl AggregateRequest {
+    /// Whether to respond with newline-delimited JSON rather than an array, same as `Accept: application/x-ndjson`.
+    pub fn stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    /// Whether to return only the first batch and a `cursorId` to continue from with `/getMore`.
+    pub fn cursor(&self) -> bool {
+        self.cursor.unwrap_or(false)
+    }
+
+    pub fn batch_size(&self) -> Option<u32> {
+        self.batch_size
+    }
 }
 
 impl MongoRequest for AggregateRequest {
@@ -24,33 +53,71 @@ impl MongoRequest for AggregateRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut aggregate_opts = AggregateOptions::default();
 
         if let Some(bypass_document_validation) = self.bypass_document_validation {
-            aggregate_opts.bypass_document_validation = Some(bypass_document_validation.clone());
+            aggregate_opts.bypass_document_validation = Some(bypass_document_validation);
         }
 
         if let Some(write_concern) = &self.write_concern {
             aggregate_opts.write_concern = Some(write_concern.clone());
         }
 
-        if let Some(batch_size) = &self.batch_size {
-            aggregate_opts.batch_size = Some(batch_size.clone());
+        if let Some(batch_size) = self.batch_size {
+            aggregate_opts.batch_size = Some(batch_size);
         }
 
         if let Some(read_concern) = &self.read_concern {
             aggregate_opts.read_concern = Some(read_concern.clone());
         }
 
-        aggregate_opts
+        if let Some(allow_disk_use) = self.allow_disk_use {
+            aggregate_opts.allow_disk_use = Some(allow_disk_use);
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            aggregate_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(hint) = &self.hint {
+            aggregate_opts.hint = Some(hint.clone());
+        }
+
+        if let Some(collation) = &self.collation {
+            aggregate_opts.collation = Some(collation.clone());
+        }
+
+        if let Some(let_vars) = &self.let_vars {
+            aggregate_opts.let_vars = Some(parse_field("let", let_vars)?);
+        }
+
+        if let Some(comment) = &self.comment {
+            aggregate_opts.comment_bson = Some(parse_value("comment", comment)?);
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            aggregate_opts.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(aggregate_opts)
+    }
+}
+
+impl Namespaced for AggregateRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl DocumentPayload for AggregateRequest {
     type PayloadType = Vec<Document>;
 
-    fn payload(&self) -> Result<Self::PayloadType, mongodb::bson::ser::Error> {
-        parse_docs(&self.pipeline)
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_docs("pipeline", &self.pipeline)
     }
 }
\ No newline at end of file
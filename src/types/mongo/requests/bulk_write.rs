@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{DeleteOptions, InsertOneOptions, ReplaceOptions, UpdateOptions, WriteConcern}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload, FilterQuery}}, utils::mongo::{parse_docs, parse_field, parse_replacement, parse_update, required_filter}};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkWriteRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    operations: Vec<WriteModel>,
+    ordered: Option<bool>,
+    bypass_document_validation: Option<bool>,
+    write_concern: Option<WriteConcern>,
+}
+
+/// A single operation of a bulk write, e.g. `{"updateOne": {"filter": {...}, "document": {...}}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteModel {
+    InsertOne(InsertOneModel),
+    UpdateOne(UpdateModel),
+    UpdateMany(UpdateModel),
+    ReplaceOne(ReplaceModel),
+    DeleteOne(DeleteModel),
+    DeleteMany(DeleteModel),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertOneModel {
+    document: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateModel {
+    filter: Option<Value>,
+    document: Value,
+    upsert: Option<bool>,
+    array_filters: Option<Vec<Value>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceModel {
+    filter: Option<Value>,
+    document: Value,
+    upsert: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteModel {
+    filter: Option<Value>,
+}
+
+/// A parsed bulk write operation, ready to be run against a collection.
+pub enum WriteOp {
+    InsertOne(Document, InsertOneOptions),
+    UpdateOne(Document, Document, UpdateOptions),
+    UpdateMany(Document, Document, UpdateOptions),
+    ReplaceOne(Document, Document, ReplaceOptions),
+    DeleteOne(Document, DeleteOptions),
+    DeleteMany(Document, DeleteOptions),
+}
+
+impl WriteOp {
+    pub fn filter(&self) -> Option<&Document> {
+        match self {
+            WriteOp::InsertOne(..) => None,
+            WriteOp::UpdateOne(filter, _, _) | WriteOp::UpdateMany(filter, _, _) | WriteOp::ReplaceOne(filter, _, _) => Some(filter),
+            WriteOp::DeleteOne(filter, _) | WriteOp::DeleteMany(filter, _) => Some(filter)
+        }
+    }
+}
+
+pub struct BulkWriteOptions {
+    pub ordered: bool,
+}
+
+impl MongoRequest for BulkWriteRequest {
+    type OptionsType = BulkWriteOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        Ok(BulkWriteOptions { ordered: self.ordered.unwrap_or(true) })
+    }
+}
+
+impl DocumentPayload for BulkWriteRequest {
+    type PayloadType = Vec<WriteOp>;
+
+    // Every operation is parsed and its documents' shape checked up front, so a malformed operation rejects the batch
+    // before anything is written. Failures the server or the connection report while running are recorded per operation.
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        self.operations
+            .iter()
+            .enumerate()
+            .map(|(i, model)| self.write_op(model).map_err(|e| e.nested(&format!("operations.{}", i))))
+            .collect()
+    }
+}
+
+impl BulkWriteRequest {
+    fn write_op(&self, model: &WriteModel) -> Result<WriteOp, ApiError> {
+        match model {
+            WriteModel::InsertOne(insert) => {
+                let mut insert_one_opts = InsertOneOptions::default();
+                insert_one_opts.bypass_document_validation = self.bypass_document_validation;
+                insert_one_opts.write_concern = self.write_concern.clone();
+
+                Ok(WriteOp::InsertOne(insert.payload()?, insert_one_opts))
+            },
+            WriteModel::UpdateOne(update) => {
+                Ok(WriteOp::UpdateOne(required_filter(update)?, update.payload()?, self.update_opts(update)?))
+            },
+            WriteModel::UpdateMany(update) => {
+                Ok(WriteOp::UpdateMany(required_filter(update)?, update.payload()?, self.update_opts(update)?))
+            },
+            WriteModel::ReplaceOne(replace) => {
+                let mut replace_opts = ReplaceOptions::default();
+                replace_opts.bypass_document_validation = self.bypass_document_validation;
+                replace_opts.write_concern = self.write_concern.clone();
+                replace_opts.upsert = replace.upsert;
+
+                Ok(WriteOp::ReplaceOne(required_filter(replace)?, replace.payload()?, replace_opts))
+            },
+            WriteModel::DeleteOne(delete) => {
+                Ok(WriteOp::DeleteOne(required_filter(delete)?, self.delete_opts()))
+            },
+            WriteModel::DeleteMany(delete) => {
+                Ok(WriteOp::DeleteMany(required_filter(delete)?, self.delete_opts()))
+            }
+        }
+    }
+
+    fn update_opts(&self, update: &UpdateModel) -> Result<UpdateOptions, ApiError> {
+        let mut update_opts = UpdateOptions::default();
+        update_opts.bypass_document_validation = self.bypass_document_validation;
+        update_opts.write_concern = self.write_concern.clone();
+        update_opts.upsert = update.upsert;
+
+        if let Some(array_filters) = &update.array_filters {
+            update_opts.array_filters = Some(parse_docs("arrayFilters", array_filters)?);
+        }
+
+        Ok(update_opts)
+    }
+
+    fn delete_opts(&self) -> DeleteOptions {
+        let mut delete_opts = DeleteOptions::default();
+        delete_opts.write_concern = self.write_concern.clone();
+        delete_opts
+    }
+}
+
+impl DocumentPayload for InsertOneModel {
+    type PayloadType = Document;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_field("document", &self.document)
+    }
+}
+
+impl DocumentPayload for UpdateModel {
+    type PayloadType = Document;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_update("document", &self.document)
+    }
+}
+
+impl FilterQuery for UpdateModel {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
+
+impl DocumentPayload for ReplaceModel {
+    type PayloadType = Document;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_replacement("document", &self.document)
+    }
+}
+
+impl FilterQuery for ReplaceModel {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
+
+impl FilterQuery for DeleteModel {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{CountOptions, Hint}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::{MongoRequest, FilterQuery, Namespaced}}}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountDocumentsRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    filter: Option<Value>,
+    skip: Option<u64>,
+    limit: Option<u64>,
+    hint: Option<Hint>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    read_preference: Option<ReadPreferenceRequest>,
+}
+
+impl MongoRequest for CountDocumentsRequest {
+    type OptionsType = CountOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut count_opts = CountOptions::default();
+
+        if let Some(skip) = self.skip {
+            count_opts.skip = Some(skip);
+        }
+
+        if let Some(limit) = self.limit {
+            count_opts.limit = Some(limit);
+        }
+
+        if let Some(hint) = &self.hint {
+            count_opts.hint = Some(hint.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            count_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            count_opts.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(count_opts)
+    }
+}
+
+impl Namespaced for CountDocumentsRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+}
+
+impl FilterQuery for CountDocumentsRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::options::{Collation, CreateCollectionOptions, TimeseriesOptions, ValidationAction, ValidationLevel, WriteConcern};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::MongoRequest}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCollectionRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    validator: Option<Value>,
+    validation_level: Option<ValidationLevel>,
+    validation_action: Option<ValidationAction>,
+    capped: Option<bool>,
+    size: Option<u64>,
+    max: Option<u64>,
+    timeseries: Option<TimeseriesOptions>,
+    expire_after_seconds: Option<u64>,
+    collation: Option<Collation>,
+    write_concern: Option<WriteConcern>,
+}
+
+impl MongoRequest for CreateCollectionRequest {
+    type OptionsType = CreateCollectionOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut create_collection_opts = CreateCollectionOptions::default();
+
+        if let Some(validator) = &self.validator {
+            create_collection_opts.validator = Some(parse_field("validator", validator)?);
+        }
+
+        if let Some(validation_level) = &self.validation_level {
+            create_collection_opts.validation_level = Some(validation_level.clone());
+        }
+
+        if let Some(validation_action) = &self.validation_action {
+            create_collection_opts.validation_action = Some(validation_action.clone());
+        }
+
+        if let Some(capped) = self.capped {
+            create_collection_opts.capped = Some(capped);
+        }
+
+        if let Some(size) = self.size {
+            create_collection_opts.size = Some(size);
+        }
+
+        if let Some(max) = self.max {
+            create_collection_opts.max = Some(max);
+        }
+
+        if let Some(timeseries) = &self.timeseries {
+            create_collection_opts.timeseries = Some(timeseries.clone());
+        }
+
+        if let Some(expire_after_seconds) = self.expire_after_seconds {
+            create_collection_opts.expire_after_seconds = Some(Duration::from_secs(expire_after_seconds));
+        }
+
+        if let Some(collation) = &self.collation {
+            create_collection_opts.collation = Some(collation.clone());
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            create_collection_opts.write_concern = Some(write_concern.clone());
+        }
+
+        Ok(create_collection_opts)
+    }
+}
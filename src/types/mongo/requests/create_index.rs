@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{Collation, CreateIndexOptions, IndexOptions, WriteConcern}, bson::Document, IndexModel};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload}}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateIndexRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    #[serde(flatten)]
+    index: IndexSpec,
+    write_concern: Option<WriteConcern>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+}
+
+/// Key spec and options of a single index, e.g. `{"keys": {"email": 1}, "unique": true}`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSpec {
+    keys: Value,
+    name: Option<String>,
+    unique: Option<bool>,
+    sparse: Option<bool>,
+    partial_filter_expression: Option<Value>,
+    expire_after_seconds: Option<u64>,
+    collation: Option<Collation>,
+}
+
+impl IndexSpec {
+    pub fn model(&self) -> Result<IndexModel, ApiError> {
+        let mut index_opts = IndexOptions::default();
+        index_opts.name = self.name.clone();
+        index_opts.unique = self.unique;
+        index_opts.sparse = self.sparse;
+        index_opts.collation = self.collation.clone();
+
+        if let Some(partial_filter_expression) = &self.partial_filter_expression {
+            index_opts.partial_filter_expression = Some(parse_field("partialFilterExpression", partial_filter_expression)?);
+        }
+
+        if let Some(expire_after_seconds) = self.expire_after_seconds {
+            index_opts.expire_after = Some(Duration::from_secs(expire_after_seconds));
+        }
+
+        let keys: Document = parse_field("keys", &self.keys)?;
+
+        Ok(IndexModel::builder().keys(keys).options(index_opts).build())
+    }
+}
+
+impl MongoRequest for CreateIndexRequest {
+    type OptionsType = CreateIndexOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut create_index_opts = CreateIndexOptions::default();
+
+        if let Some(write_concern) = &self.write_concern {
+            create_index_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            create_index_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        Ok(create_index_opts)
+    }
+}
+
+impl DocumentPayload for CreateIndexRequest {
+    type PayloadType = IndexModel;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        self.index.model()
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::{options::{CreateIndexOptions, WriteConcern}, IndexModel};
+
+use crate::types::{errors::ApiError, mongo::{requests::create_index::IndexSpec, traits::requests::{MongoRequest, DocumentPayload}}};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateIndexesRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    indexes: Vec<IndexSpec>,
+    write_concern: Option<WriteConcern>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+}
+
+impl MongoRequest for CreateIndexesRequest {
+    type OptionsType = CreateIndexOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut create_indexes_opts = CreateIndexOptions::default();
+
+        if let Some(write_concern) = &self.write_concern {
+            create_indexes_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            create_indexes_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        Ok(create_indexes_opts)
+    }
+}
+
+impl DocumentPayload for CreateIndexesRequest {
+    type PayloadType = Vec<IndexModel>;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        self.indexes
+            .iter()
+            .enumerate()
+            .map(|(i, index)| index.model().map_err(|e| e.nested(&format!("indexes.{}", i))))
+            .collect()
+    }
+}
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::{DeleteOptions, WriteConcern}, bson::{self, Document}};
+use mongodb::{options::{Collation, DeleteOptions, Hint, WriteConcern}, bson::Document};
 
-use crate::types::mongo::traits::requests::{FilterQuery, MongoRequest};
+use crate::{types::{errors::ApiError, mongo::traits::requests::{FilterQuery, MongoRequest, Namespaced}}, utils::mongo::{parse_field, parse_value}};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -11,7 +11,12 @@ pub struct DeleteRequest {
     database: Option<String>,
     collection: String,
     filter: Option<Value>,
-    write_concern: Option<WriteConcern>
+    write_concern: Option<WriteConcern>,
+    collation: Option<Collation>,
+    hint: Option<Hint>,
+    #[serde(rename = "let")]
+    let_vars: Option<Value>,
+    comment: Option<Value>,
 }
 
 impl MongoRequest for DeleteRequest {
@@ -21,26 +26,45 @@ impl MongoRequest for DeleteRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut del_options = DeleteOptions::default();
 
         if let Some(write_concern) = &self.write_concern {
             del_options.write_concern = Some(write_concern.clone());
         }
 
-        del_options
+        if let Some(collation) = &self.collation {
+            del_options.collation = Some(collation.clone());
+        }
+
+        if let Some(hint) = &self.hint {
+            del_options.hint = Some(hint.clone());
+        }
+
+        if let Some(let_vars) = &self.let_vars {
+            del_options.let_vars = Some(parse_field("let", let_vars)?);
+        }
+
+        if let Some(comment) = &self.comment {
+            del_options.comment = Some(parse_value("comment", comment)?);
+        }
+
+        Ok(del_options)
+    }
+}
+
+impl Namespaced for DeleteRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl FilterQuery for DeleteRequest {
-    fn filter(&self) -> Option<Result<Document, bson::ser::Error>> {
-        if let Some(json) = &self.filter {
-            match bson::to_document(json) {
-                Ok(doc) => Some(Ok(doc)),
-                Err(e) => Some(Err(e.into())) 
-            }
-        } else {
-            None
-        }
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
     }
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::DistinctOptions, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::{MongoRequest, FilterQuery, Namespaced}}}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DistinctRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    key: String,
+    filter: Option<Value>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    read_preference: Option<ReadPreferenceRequest>,
+}
+
+impl DistinctRequest {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl MongoRequest for DistinctRequest {
+    type OptionsType = DistinctOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut distinct_opts = DistinctOptions::default();
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            distinct_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            distinct_opts.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(distinct_opts)
+    }
+}
+
+impl Namespaced for DistinctRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+}
+
+impl FilterQuery for DistinctRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use mongodb::options::{DropCollectionOptions, WriteConcern};
+
+use crate::types::{errors::ApiError, mongo::traits::requests::MongoRequest};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropCollectionRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    write_concern: Option<WriteConcern>,
+}
+
+impl MongoRequest for DropCollectionRequest {
+    type OptionsType = DropCollectionOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut drop_collection_opts = DropCollectionOptions::default();
+
+        if let Some(write_concern) = &self.write_concern {
+            drop_collection_opts.write_concern = Some(write_concern.clone());
+        }
+
+        Ok(drop_collection_opts)
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::options::{DropIndexOptions, WriteConcern};
+
+use crate::types::{errors::ApiError, mongo::traits::requests::MongoRequest};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropIndexRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    name: String,
+    write_concern: Option<WriteConcern>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+}
+
+impl DropIndexRequest {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl MongoRequest for DropIndexRequest {
+    type OptionsType = DropIndexOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut drop_index_opts = DropIndexOptions::default();
+
+        if let Some(write_concern) = &self.write_concern {
+            drop_index_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            drop_index_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        Ok(drop_index_opts)
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::options::EstimatedDocumentCountOptions;
+
+use crate::types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::MongoRequest}};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimatedDocumentCountRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    read_preference: Option<ReadPreferenceRequest>,
+}
+
+impl MongoRequest for EstimatedDocumentCountRequest {
+    type OptionsType = EstimatedDocumentCountOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut estimated_count_opts = EstimatedDocumentCountOptions::default();
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            estimated_count_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            estimated_count_opts.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(estimated_count_opts)
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{GridFsBucketOptions, GridFsFindOptions}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::FilterQuery}, utils::mongo::parse_field};
+
+// Bucket used when a request does not name one, same as the drivers
+const DEFAULT_BUCKET: &str = "fs";
+
+// Each chunk is one document, which has to stay under the 16 MiB document limit along with its other fields
+const MAX_CHUNK_SIZE_BYTES: u32 = 16 * 1024 * 1024 - 16 * 1024;
+
+/// Query string of the `/files/:id` routes, e.g. `?database=app&bucket=attachments`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketQuery {
+    data_source: Option<String>,
+    database: Option<String>,
+    bucket: Option<String>,
+}
+
+/// Query string of `/files/upload`. A multipart upload may send `filename`, `contentType` and `metadata`
+/// as form fields instead, ahead of the `file` field.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadQuery {
+    data_source: Option<String>,
+    database: Option<String>,
+    bucket: Option<String>,
+    filename: Option<String>,
+    content_type: Option<String>,
+    /// Extended JSON document, URL encoded
+    metadata: Option<String>,
+    chunk_size_bytes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindFilesRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    bucket: Option<String>,
+    filter: Option<Value>,
+    sort: Option<Value>,
+    limit: Option<i64>,
+    skip: Option<u64>,
+    batch_size: Option<u32>,
+}
+
+impl BucketQuery {
+    pub fn bucket(&self) -> &str {
+        self.bucket.as_deref().unwrap_or(DEFAULT_BUCKET)
+    }
+
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    pub fn opts(&self) -> GridFsBucketOptions {
+        bucket_opts(self.bucket.as_deref())
+    }
+}
+
+impl UploadQuery {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn chunk_size_bytes(&self) -> Result<Option<u32>, ApiError> {
+        match self.chunk_size_bytes {
+            Some(0) => Err(ApiError::invalid_field("chunkSizeBytes", "must be positive")),
+            Some(size) if size > MAX_CHUNK_SIZE_BYTES => {
+                Err(ApiError::invalid_field("chunkSizeBytes", format!("must be at most {}", MAX_CHUNK_SIZE_BYTES)))
+            },
+            size => Ok(size)
+        }
+    }
+
+    pub fn metadata(&self) -> Option<Result<Document, ApiError>> {
+        self.metadata.as_deref().map(parse_metadata)
+    }
+
+    pub fn opts(&self) -> GridFsBucketOptions {
+        bucket_opts(self.bucket.as_deref())
+    }
+}
+
+impl FindFilesRequest {
+    pub fn bucket(&self) -> &str {
+        self.bucket.as_deref().unwrap_or(DEFAULT_BUCKET)
+    }
+
+    pub fn bucket_opts(&self) -> GridFsBucketOptions {
+        bucket_opts(self.bucket.as_deref())
+    }
+
+    pub fn opts(&self) -> Result<GridFsFindOptions, ApiError> {
+        let mut find_opts = GridFsFindOptions::default();
+
+        if let Some(sort) = &self.sort {
+            find_opts.sort = Some(parse_field("sort", sort)?);
+        }
+
+        if let Some(limit) = self.limit {
+            find_opts.limit = Some(limit);
+        }
+
+        if let Some(skip) = self.skip {
+            find_opts.skip = Some(skip);
+        }
+
+        if let Some(batch_size) = self.batch_size {
+            find_opts.batch_size = Some(batch_size);
+        }
+
+        Ok(find_opts)
+    }
+}
+
+impl FilterQuery for FindFilesRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
+
+fn bucket_opts(bucket: Option<&str>) -> GridFsBucketOptions {
+    let mut bucket_opts = GridFsBucketOptions::default();
+    bucket_opts.bucket_name = Some(bucket.unwrap_or(DEFAULT_BUCKET).to_string());
+
+    bucket_opts
+}
+
+/// Parses the `metadata` of an upload, sent as an Extended JSON string in the query or a form field.
+pub fn parse_metadata(metadata: &str) -> Result<Document, ApiError> {
+    let json: Value = serde_json::from_str(metadata).map_err(|e| ApiError::invalid_field("metadata", e))?;
+
+    parse_field("metadata", &json)
+}
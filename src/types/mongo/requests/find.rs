@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::FindOptions, bson::{self, Document}};
+use mongodb::{options::{Collation, FindOptions, Hint, ReadConcern}, bson::Document};
 
-use crate::types::mongo::traits::requests::{MongoRequest, FilterQuery};
+use crate::{types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::{MongoRequest, FilterQuery, Namespaced}}}, utils::mongo::{parse_field, parse_value}};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,6 +17,50 @@ pub struct FindRequest {
     sort: Option<Value>,
     limit: Option<i64>,
     skip: Option<u64>,
+    batch_size: Option<u32>,
+    stream: Option<bool>,
+    cursor: Option<bool>,
+    page_size: Option<i64>,
+    page_token: Option<String>,
+    hint: Option<Hint>,
+    collation: Option<Collation>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    allow_disk_use: Option<bool>,
+    comment: Option<Value>,
+    #[serde(rename = "let")]
+    let_vars: Option<Value>,
+    min: Option<Value>,
+    max: Option<Value>,
+    return_key: Option<bool>,
+    show_record_id: Option<bool>,
+    no_cursor_timeout: Option<bool>,
+    read_concern: Option<ReadConcern>,
+    read_preference: Option<ReadPreferenceRequest>,
+}
+
+impl FindRequest {
+    /// Whether to respond with newline-delimited JSON rather than an array, same as `Accept: application/x-ndjson`.
+    pub fn stream(&self) -> bool {
+        self.stream.unwrap_or(false)
+    }
+
+    /// Whether to return only the first batch and a `cursorId` to continue from with `/getMore`.
+    pub fn cursor(&self) -> bool {
+        self.cursor.unwrap_or(false)
+    }
+
+    pub fn batch_size(&self) -> Option<u32> {
+        self.batch_size
+    }
+
+    pub fn page_size(&self) -> Option<i64> {
+        self.page_size
+    }
+
+    pub fn page_token(&self) -> Option<&str> {
+        self.page_token.as_deref()
+    }
 }
 
 impl MongoRequest for FindRequest {
@@ -24,7 +70,7 @@ impl MongoRequest for FindRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut find_options = FindOptions::default();
 
         if let Some(limit) = self.limit {
@@ -35,31 +81,86 @@ impl MongoRequest for FindRequest {
             find_options.skip = Some(skip);
         }
 
+        if let Some(batch_size) = self.batch_size {
+            find_options.batch_size = Some(batch_size);
+        }
+
         if let Some(sort) = &self.sort {
-            if let Ok(doc) = bson::to_document(sort) {
-                find_options.sort = Some(doc);
-            }
+            find_options.sort = Some(parse_field("sort", sort)?);
         }
 
         if let Some(projection) = &self.projection {
-            if let Ok(doc) = bson::to_document(projection) {
-                find_options.projection = Some(doc);
-            }
+            find_options.projection = Some(parse_field("projection", projection)?);
+        }
+
+        if let Some(hint) = &self.hint {
+            find_options.hint = Some(hint.clone());
+        }
+
+        if let Some(collation) = &self.collation {
+            find_options.collation = Some(collation.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            find_options.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(allow_disk_use) = self.allow_disk_use {
+            find_options.allow_disk_use = Some(allow_disk_use);
+        }
+
+        if let Some(comment) = &self.comment {
+            find_options.comment_bson = Some(parse_value("comment", comment)?);
+        }
+
+        if let Some(let_vars) = &self.let_vars {
+            find_options.let_vars = Some(parse_field("let", let_vars)?);
+        }
+
+        if let Some(min) = &self.min {
+            find_options.min = Some(parse_field("min", min)?);
+        }
+
+        if let Some(max) = &self.max {
+            find_options.max = Some(parse_field("max", max)?);
+        }
+
+        if let Some(return_key) = self.return_key {
+            find_options.return_key = Some(return_key);
         }
 
-        find_options
+        if let Some(show_record_id) = self.show_record_id {
+            find_options.show_record_id = Some(show_record_id);
+        }
+
+        if let Some(no_cursor_timeout) = self.no_cursor_timeout {
+            find_options.no_cursor_timeout = Some(no_cursor_timeout);
+        }
+
+        if let Some(read_concern) = &self.read_concern {
+            find_options.read_concern = Some(read_concern.clone());
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            find_options.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(find_options)
+    }
+}
+
+impl Namespaced for FindRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl FilterQuery for FindRequest {
-    fn filter(&self) -> Option<Result<Document, bson::ser::Error>> {
-        if let Some(json) = &self.filter {
-            match bson::to_document(json) {
-                Ok(doc) => Some(Ok(doc)),
-                Err(e) => Some(Err(e.into())) 
-            }
-        } else {
-            None
-        }
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
     }
 }
\ No newline at end of file
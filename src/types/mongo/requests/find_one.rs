@@ -1,8 +1,10 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::FindOneOptions, bson::{self, Document}};
+use mongodb::{options::{Collation, FindOneOptions, Hint, ReadConcern}, bson::Document};
 
-use crate::{types::mongo::traits::requests::{MongoRequest, FilterQuery}, utils::mongo::parse_filter};
+use crate::{types::{errors::ApiError, mongo::{read_preference::ReadPreferenceRequest, traits::requests::{MongoRequest, FilterQuery, Namespaced}}}, utils::mongo::{parse_field, parse_value}};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,6 +14,21 @@ pub struct FindOneRequest {
     collection: String,
     filter: Option<Value>,
     projection: Option<Value>,
+    sort: Option<Value>,
+    skip: Option<u64>,
+    hint: Option<Hint>,
+    collation: Option<Collation>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+    comment: Option<Value>,
+    #[serde(rename = "let")]
+    let_vars: Option<Value>,
+    min: Option<Value>,
+    max: Option<Value>,
+    return_key: Option<bool>,
+    show_record_id: Option<bool>,
+    read_concern: Option<ReadConcern>,
+    read_preference: Option<ReadPreferenceRequest>,
 }
 
 impl MongoRequest for FindOneRequest {
@@ -21,25 +38,81 @@ impl MongoRequest for FindOneRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut find_options = FindOneOptions::default();
 
         if let Some(projection) = &self.projection {
-            if let Ok(doc) = bson::to_document(projection) {
-                find_options.projection = Some(doc);
-            }
+            find_options.projection = Some(parse_field("projection", projection)?);
+        }
+
+        if let Some(sort) = &self.sort {
+            find_options.sort = Some(parse_field("sort", sort)?);
+        }
+
+        if let Some(skip) = self.skip {
+            find_options.skip = Some(skip);
+        }
+
+        if let Some(hint) = &self.hint {
+            find_options.hint = Some(hint.clone());
+        }
+
+        if let Some(collation) = &self.collation {
+            find_options.collation = Some(collation.clone());
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            find_options.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        if let Some(comment) = &self.comment {
+            find_options.comment_bson = Some(parse_value("comment", comment)?);
+        }
+
+        if let Some(let_vars) = &self.let_vars {
+            find_options.let_vars = Some(parse_field("let", let_vars)?);
+        }
+
+        if let Some(min) = &self.min {
+            find_options.min = Some(parse_field("min", min)?);
         }
 
-        find_options
+        if let Some(max) = &self.max {
+            find_options.max = Some(parse_field("max", max)?);
+        }
+
+        if let Some(return_key) = self.return_key {
+            find_options.return_key = Some(return_key);
+        }
+
+        if let Some(show_record_id) = self.show_record_id {
+            find_options.show_record_id = Some(show_record_id);
+        }
+
+        if let Some(read_concern) = &self.read_concern {
+            find_options.read_concern = Some(read_concern.clone());
+        }
+
+        if let Some(read_preference) = &self.read_preference {
+            find_options.selection_criteria = Some(read_preference.selection_criteria()?);
+        }
+
+        Ok(find_options)
+    }
+}
+
+impl Namespaced for FindOneRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl FilterQuery for FindOneRequest {
-    fn filter(&self) -> Option<Result<Document, bson::ser::Error>> {
-        if let Some(json) = &self.filter {
-            Some(parse_filter(json))
-        } else {
-            None
-        }
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
     }
 }
\ No newline at end of file
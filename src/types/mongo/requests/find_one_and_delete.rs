@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{FindOneAndDeleteOptions, WriteConcern}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, FilterQuery, Namespaced}}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindOneAndDeleteRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    filter: Option<Value>,
+    projection: Option<Value>,
+    sort: Option<Value>,
+    write_concern: Option<WriteConcern>,
+}
+
+impl MongoRequest for FindOneAndDeleteRequest {
+    type OptionsType = FindOneAndDeleteOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut find_one_and_delete_opts = FindOneAndDeleteOptions::default();
+
+        if let Some(projection) = &self.projection {
+            find_one_and_delete_opts.projection = Some(parse_field("projection", projection)?);
+        }
+
+        if let Some(sort) = &self.sort {
+            find_one_and_delete_opts.sort = Some(parse_field("sort", sort)?);
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            find_one_and_delete_opts.write_concern = Some(write_concern.clone());
+        }
+
+        Ok(find_one_and_delete_opts)
+    }
+}
+
+impl Namespaced for FindOneAndDeleteRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+}
+
+impl FilterQuery for FindOneAndDeleteRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{FindOneAndReplaceOptions, ReturnDocument, WriteConcern}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload, FilterQuery, Namespaced}}, utils::mongo::{parse_field, parse_replacement}};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindOneAndReplaceRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    filter: Option<Value>,
+    document: Value,
+    projection: Option<Value>,
+    sort: Option<Value>,
+    #[serde(skip_serializing)]
+    return_document: Option<ReturnDocument>,
+    bypass_document_validation: Option<bool>,
+    write_concern: Option<WriteConcern>,
+    upsert: Option<bool>,
+}
+
+impl MongoRequest for FindOneAndReplaceRequest {
+    type OptionsType = FindOneAndReplaceOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut find_one_and_replace_opts = FindOneAndReplaceOptions::default();
+
+        if let Some(projection) = &self.projection {
+            find_one_and_replace_opts.projection = Some(parse_field("projection", projection)?);
+        }
+
+        if let Some(sort) = &self.sort {
+            find_one_and_replace_opts.sort = Some(parse_field("sort", sort)?);
+        }
+
+        if let Some(return_document) = &self.return_document {
+            find_one_and_replace_opts.return_document = Some(return_document.clone());
+        }
+
+        if let Some(bypass_document_validation) = self.bypass_document_validation {
+            find_one_and_replace_opts.bypass_document_validation = Some(bypass_document_validation);
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            find_one_and_replace_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(upsert) = self.upsert {
+            find_one_and_replace_opts.upsert = Some(upsert);
+        }
+
+        Ok(find_one_and_replace_opts)
+    }
+}
+
+impl Namespaced for FindOneAndReplaceRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+}
+
+impl DocumentPayload for FindOneAndReplaceRequest {
+    type PayloadType = Document;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_replacement("document", &self.document)
+    }
+}
+
+impl FilterQuery for FindOneAndReplaceRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
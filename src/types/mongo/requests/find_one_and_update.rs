@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{FindOneAndUpdateOptions, ReturnDocument, WriteConcern}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload, FilterQuery, Namespaced}}, utils::mongo::{parse_docs, parse_field, parse_update}};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindOneAndUpdateRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    filter: Option<Value>,
+    document: Value,
+    projection: Option<Value>,
+    sort: Option<Value>,
+    #[serde(skip_serializing)]
+    return_document: Option<ReturnDocument>,
+    bypass_document_validation: Option<bool>,
+    write_concern: Option<WriteConcern>,
+    upsert: Option<bool>,
+    array_filters: Option<Vec<Value>>,
+}
+
+impl MongoRequest for FindOneAndUpdateRequest {
+    type OptionsType = FindOneAndUpdateOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut find_one_and_update_opts = FindOneAndUpdateOptions::default();
+
+        if let Some(projection) = &self.projection {
+            find_one_and_update_opts.projection = Some(parse_field("projection", projection)?);
+        }
+
+        if let Some(sort) = &self.sort {
+            find_one_and_update_opts.sort = Some(parse_field("sort", sort)?);
+        }
+
+        if let Some(return_document) = &self.return_document {
+            find_one_and_update_opts.return_document = Some(return_document.clone());
+        }
+
+        if let Some(bypass_document_validation) = self.bypass_document_validation {
+            find_one_and_update_opts.bypass_document_validation = Some(bypass_document_validation);
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            find_one_and_update_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(upsert) = self.upsert {
+            find_one_and_update_opts.upsert = Some(upsert);
+        }
+
+        if let Some(array_filters) = &self.array_filters {
+            find_one_and_update_opts.array_filters = Some(parse_docs("arrayFilters", array_filters)?);
+        }
+
+        Ok(find_one_and_update_opts)
+    }
+}
+
+impl Namespaced for FindOneAndUpdateRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+}
+
+impl DocumentPayload for FindOneAndUpdateRequest {
+    type PayloadType = Document;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_update("document", &self.document)
+    }
+}
+
+impl FilterQuery for FindOneAndUpdateRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
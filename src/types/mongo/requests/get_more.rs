@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetMoreRequest {
+    cursor_id: String,
+    batch_size: Option<u32>,
+}
+
+// Cursors are already bound to their collection, so this does not implement MongoRequest
+impl GetMoreRequest {
+    pub fn cursor_id(&self) -> &str {
+        &self.cursor_id
+    }
+
+    pub fn batch_size(&self) -> Option<u32> {
+        self.batch_size
+    }
+}
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::{InsertManyOptions, WriteConcern}, bson::{self, Document}};
+use mongodb::{options::{InsertManyOptions, WriteConcern}, bson::Document};
 
-use crate::{types::mongo::traits::requests::{MongoRequest, DocumentPayload}, utils::mongo::parse_docs};
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload, Namespaced}}, utils::mongo::parse_docs};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -23,11 +23,11 @@ impl MongoRequest for InsertManyRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut insert_many_opts = InsertManyOptions::default();
 
         if let Some(bypass_document_validation) = self.bypass_document_validation {
-            insert_many_opts.bypass_document_validation = Some(bypass_document_validation.clone());
+            insert_many_opts.bypass_document_validation = Some(bypass_document_validation);
         }
 
         if let Some(write_concern) = &self.write_concern {
@@ -35,17 +35,27 @@ impl MongoRequest for InsertManyRequest {
         }
 
         if let Some(ordered) = self.ordered {
-            insert_many_opts.ordered = Some(ordered.clone());
+            insert_many_opts.ordered = Some(ordered);
         }
 
-        insert_many_opts
+        Ok(insert_many_opts)
+    }
+}
+
+impl Namespaced for InsertManyRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl DocumentPayload for InsertManyRequest {
     type PayloadType = Vec<Document>;
 
-    fn payload(&self) -> Result<Self::PayloadType, bson::ser::Error> {
-        parse_docs(&self.documents)
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_docs("documents", &self.documents)
     }
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::{InsertOneOptions, WriteConcern}, bson::{self, Document}};
+use mongodb::{options::{InsertOneOptions, WriteConcern}, bson::Document};
 
-use crate::types::mongo::traits::requests::{MongoRequest, DocumentPayload};
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, DocumentPayload, Namespaced}}, utils::mongo::parse_field};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,25 +22,35 @@ impl MongoRequest for InsertOneRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut insert_one_opts = InsertOneOptions::default();
 
         if let Some(bypass_document_validation) = self.bypass_document_validation {
-            insert_one_opts.bypass_document_validation = Some(bypass_document_validation.clone());
+            insert_one_opts.bypass_document_validation = Some(bypass_document_validation);
         }
 
         if let Some(write_concern) = &self.write_concern {
             insert_one_opts.write_concern = Some(write_concern.clone());
         }
 
-        insert_one_opts
+        Ok(insert_one_opts)
+    }
+}
+
+impl Namespaced for InsertOneRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
 impl DocumentPayload for InsertOneRequest {
     type PayloadType = Document;
 
-    fn payload(&self) -> Result<Self::PayloadType, bson::ser::Error> {
-        bson::to_document(&self.document)
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        parse_field("document", &self.document)
     }
 }
\ No newline at end of file
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KillCursorsRequest {
+    cursor_ids: Vec<String>,
+}
+
+impl KillCursorsRequest {
+    pub fn cursor_ids(&self) -> &[String] {
+        &self.cursor_ids
+    }
+}
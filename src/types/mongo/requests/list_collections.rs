@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::ListCollectionsOptions, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::FilterQuery}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListCollectionsRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    filter: Option<Value>,
+    name_only: Option<bool>,
+    batch_size: Option<u32>,
+}
+
+impl ListCollectionsRequest {
+    pub fn name_only(&self) -> bool {
+        self.name_only.unwrap_or(false)
+    }
+
+    pub fn opts(&self) -> ListCollectionsOptions {
+        let mut list_collections_opts = ListCollectionsOptions::default();
+
+        if let Some(batch_size) = self.batch_size {
+            list_collections_opts.batch_size = Some(batch_size);
+        }
+
+        list_collections_opts
+    }
+}
+
+impl FilterQuery for ListCollectionsRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::ListDatabasesOptions, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::FilterQuery}, utils::mongo::parse_field};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListDatabasesRequest {
+    data_source: Option<String>,
+    filter: Option<Value>,
+    name_only: Option<bool>,
+    authorized_databases: Option<bool>,
+}
+
+impl ListDatabasesRequest {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn name_only(&self) -> bool {
+        self.name_only.unwrap_or(false)
+    }
+
+    pub fn opts(&self) -> ListDatabasesOptions {
+        let mut list_databases_opts = ListDatabasesOptions::default();
+
+        if let Some(authorized_databases) = self.authorized_databases {
+            list_databases_opts.authorized_databases = Some(authorized_databases);
+        }
+
+        list_databases_opts
+    }
+}
+
+impl FilterQuery for ListDatabasesRequest {
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::options::ListIndexesOptions;
+
+use crate::types::{errors::ApiError, mongo::traits::requests::MongoRequest};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListIndexesRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    batch_size: Option<u32>,
+    #[serde(rename = "maxTimeMS")]
+    max_time_ms: Option<u64>,
+}
+
+impl MongoRequest for ListIndexesRequest {
+    type OptionsType = ListIndexesOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        let mut list_indexes_opts = ListIndexesOptions::default();
+
+        if let Some(batch_size) = self.batch_size {
+            list_indexes_opts.batch_size = Some(batch_size);
+        }
+
+        if let Some(max_time_ms) = self.max_time_ms {
+            list_indexes_opts.max_time = Some(Duration::from_millis(max_time_ms));
+        }
+
+        Ok(list_indexes_opts)
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use mongodb::options::WriteConcern;
+
+use crate::types::{errors::ApiError, mongo::traits::requests::MongoRequest};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameCollectionRequest {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: String,
+    to: String,
+    to_database: Option<String>,
+    drop_target: Option<bool>,
+    write_concern: Option<WriteConcern>,
+}
+
+/// The driver has no rename helper, these become fields of the `renameCollection` command.
+pub struct RenameCollectionOptions {
+    pub drop_target: bool,
+    pub write_concern: Option<WriteConcern>,
+}
+
+impl RenameCollectionRequest {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    /// Target database, defaults to the source collection's database.
+    pub fn to_database(&self) -> Option<&str> {
+        self.to_database.as_deref().or(self.database.as_deref())
+    }
+}
+
+impl MongoRequest for RenameCollectionRequest {
+    type OptionsType = RenameCollectionOptions;
+
+    fn coll(&self) -> &str {
+        &self.collection
+    }
+
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
+        Ok(RenameCollectionOptions {
+            drop_target: self.drop_target.unwrap_or(false),
+            write_concern: self.write_concern.clone()
+        })
+    }
+}
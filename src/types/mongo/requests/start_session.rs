@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::options::{ReadConcern, TransactionOptions, WriteConcern};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartSessionRequest {
+    data_source: Option<String>,
+    read_concern: Option<ReadConcern>,
+    write_concern: Option<WriteConcern>,
+    #[serde(rename = "maxCommitTimeMS")]
+    max_commit_time_ms: Option<u64>,
+}
+
+impl StartSessionRequest {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn opts(&self) -> TransactionOptions {
+        let mut transaction_opts = TransactionOptions::default();
+
+        if let Some(read_concern) = &self.read_concern {
+            transaction_opts.read_concern = Some(read_concern.clone());
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            transaction_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(max_commit_time_ms) = self.max_commit_time_ms {
+            transaction_opts.max_commit_time = Some(Duration::from_millis(max_commit_time_ms));
+        }
+
+        transaction_opts
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::mongo::requests::find::FindRequest;
+
+/// Message sent by a client over `/ws`, e.g. `{"action": "subscribe", "id": "todos", "query": {"collection": "todos", "filter": {...}}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum SubscriptionMessage {
+    Subscribe { id: String, query: Box<FindRequest> },
+    Unsubscribe { id: String },
+}
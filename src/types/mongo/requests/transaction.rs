@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use mongodb::options::{ReadConcern, TransactionOptions, WriteConcern};
+
+use crate::{types::{errors::ApiError, mongo::{operation::Operation, requests::{aggregate::AggregateRequest, count_documents::CountDocumentsRequest, delete::DeleteRequest, distinct::DistinctRequest, find::FindRequest, find_one::FindOneRequest, find_one_and_delete::FindOneAndDeleteRequest, find_one_and_replace::FindOneAndReplaceRequest, find_one_and_update::FindOneAndUpdateRequest, insert_many::InsertManyRequest, insert_one::InsertOneRequest, update::{UpdateOptionsWrapper, UpdateRequest}}, traits::requests::{DocumentPayload, FilterQuery, MongoRequest, Namespaced}}}, utils::mongo::required_filter};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRequest {
+    data_source: Option<String>,
+    operations: Vec<TransactionOperation>,
+    read_concern: Option<ReadConcern>,
+    write_concern: Option<WriteConcern>,
+    #[serde(rename = "maxCommitTimeMS")]
+    max_commit_time_ms: Option<u64>,
+}
+
+/// One step of a transaction, using the same body as the matching route, e.g. `{"insertOne": {"collection": ..., "document": ...}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionOperation {
+    Find(FindRequest),
+    FindOne(FindOneRequest),
+    FindOneAndUpdate(FindOneAndUpdateRequest),
+    FindOneAndReplace(FindOneAndReplaceRequest),
+    FindOneAndDelete(FindOneAndDeleteRequest),
+    InsertOne(InsertOneRequest),
+    InsertMany(InsertManyRequest),
+    UpdateOne(UpdateRequest),
+    UpdateMany(UpdateRequest),
+    ReplaceOne(UpdateRequest),
+    DeleteOne(DeleteRequest),
+    DeleteMany(DeleteRequest),
+    Aggregate(AggregateRequest),
+    CountDocuments(CountDocumentsRequest),
+    Distinct(DistinctRequest),
+}
+
+impl TransactionRequest {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn operations(&self) -> &[TransactionOperation] {
+        &self.operations
+    }
+
+    pub fn opts(&self) -> TransactionOptions {
+        let mut transaction_opts = TransactionOptions::default();
+
+        if let Some(read_concern) = &self.read_concern {
+            transaction_opts.read_concern = Some(read_concern.clone());
+        }
+
+        if let Some(write_concern) = &self.write_concern {
+            transaction_opts.write_concern = Some(write_concern.clone());
+        }
+
+        if let Some(max_commit_time_ms) = self.max_commit_time_ms {
+            transaction_opts.max_commit_time = Some(Duration::from_millis(max_commit_time_ms));
+        }
+
+        transaction_opts
+    }
+}
+
+impl TransactionOperation {
+    fn request(&self) -> &dyn Namespaced {
+        match self {
+            TransactionOperation::Find(r) => r,
+            TransactionOperation::FindOne(r) => r,
+            TransactionOperation::FindOneAndUpdate(r) => r,
+            TransactionOperation::FindOneAndReplace(r) => r,
+            TransactionOperation::FindOneAndDelete(r) => r,
+            TransactionOperation::InsertOne(r) => r,
+            TransactionOperation::InsertMany(r) => r,
+            TransactionOperation::UpdateOne(r) | TransactionOperation::UpdateMany(r) | TransactionOperation::ReplaceOne(r) => r,
+            TransactionOperation::DeleteOne(r) | TransactionOperation::DeleteMany(r) => r,
+            TransactionOperation::Aggregate(r) => r,
+            TransactionOperation::CountDocuments(r) => r,
+            TransactionOperation::Distinct(r) => r
+        }
+    }
+
+    pub fn data_source(&self) -> Option<&str> {
+        self.request().data_source()
+    }
+
+    pub fn database(&self) -> Option<&str> {
+        self.request().database()
+    }
+
+    pub fn coll(&self) -> &str {
+        match self {
+            TransactionOperation::Find(r) => r.coll(),
+            TransactionOperation::FindOne(r) => r.coll(),
+            TransactionOperation::FindOneAndUpdate(r) => r.coll(),
+            TransactionOperation::FindOneAndReplace(r) => r.coll(),
+            TransactionOperation::FindOneAndDelete(r) => r.coll(),
+            TransactionOperation::InsertOne(r) => r.coll(),
+            TransactionOperation::InsertMany(r) => r.coll(),
+            TransactionOperation::UpdateOne(r) | TransactionOperation::UpdateMany(r) | TransactionOperation::ReplaceOne(r) => r.coll(),
+            TransactionOperation::DeleteOne(r) | TransactionOperation::DeleteMany(r) => r.coll(),
+            TransactionOperation::Aggregate(r) => r.coll(),
+            TransactionOperation::CountDocuments(r) => r.coll(),
+            TransactionOperation::Distinct(r) => r.coll()
+        }
+    }
+
+    pub fn operation(&self) -> Result<Operation, ApiError> {
+        match self {
+            TransactionOperation::Find(r) => {
+                unsupported(&[("stream", r.stream()), ("cursor", r.cursor()), ("pageSize", r.page_size().is_some()), ("pageToken", r.page_token().is_some())])?;
+                Ok(Operation::Find(r.filter().transpose()?, r.opts()?))
+            },
+            TransactionOperation::FindOne(r) => Ok(Operation::FindOne(r.filter().transpose()?, r.opts()?)),
+            TransactionOperation::FindOneAndUpdate(r) => Ok(Operation::FindOneAndUpdate(required_filter(r)?, r.payload()?, r.opts()?)),
+            TransactionOperation::FindOneAndReplace(r) => Ok(Operation::FindOneAndReplace(required_filter(r)?, r.payload()?, r.opts()?)),
+            TransactionOperation::FindOneAndDelete(r) => Ok(Operation::FindOneAndDelete(required_filter(r)?, r.opts()?)),
+            TransactionOperation::InsertOne(r) => Ok(Operation::InsertOne(r.payload()?, r.opts()?)),
+            TransactionOperation::InsertMany(r) => Ok(Operation::InsertMany(r.payload()?, r.opts()?)),
+            TransactionOperation::UpdateOne(r) => Ok(Operation::UpdateOne(required_filter(r)?, r.update()?, r.opts()?)),
+            TransactionOperation::UpdateMany(r) => Ok(Operation::UpdateMany(required_filter(r)?, r.update()?, r.opts()?)),
+            TransactionOperation::ReplaceOne(r) => Ok(Operation::ReplaceOne(required_filter(r)?, r.replacement()?, UpdateOptionsWrapper(r.opts()?).try_into()?)),
+            TransactionOperation::DeleteOne(r) => Ok(Operation::DeleteOne(required_filter(r)?, r.opts()?)),
+            TransactionOperation::DeleteMany(r) => Ok(Operation::DeleteMany(required_filter(r)?, r.opts()?)),
+            TransactionOperation::Aggregate(r) => {
+                unsupported(&[("stream", r.stream()), ("cursor", r.cursor())])?;
+                Ok(Operation::Aggregate(r.payload()?, r.opts()?))
+            },
+            TransactionOperation::CountDocuments(r) => Ok(Operation::CountDocuments(r.filter().transpose()?, r.opts()?)),
+            TransactionOperation::Distinct(r) => Ok(Operation::Distinct(r.key().to_string(), r.filter().transpose()?, r.opts()?))
+        }
+    }
+}
+
+// The results of every step are returned whole in the response, so they cannot be streamed, paged or left in a cursor
+fn unsupported(fields: &[(&str, bool)]) -> Result<(), ApiError> {
+    match fields.iter().find(|(_, set)| *set) {
+        Some((field, _)) => Err(ApiError::invalid_field(*field, "is not supported in a transaction")),
+        None => Ok(())
+    }
+}
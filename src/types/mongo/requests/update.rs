@@ -1,10 +1,10 @@
-use std::convert::From;
+use std::convert::TryFrom;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use mongodb::{options::{UpdateOptions, WriteConcern, ReplaceOptions}, bson::{self, Document}};
+use mongodb::{options::{Collation, Hint, UpdateOptions, WriteConcern, ReplaceOptions}, bson::Document};
 
-use crate::{types::mongo::traits::requests::{MongoRequest, DocumentPayload, FilterQuery}, utils::mongo::{parse_docs, parse_filter}};
+use crate::{types::{errors::ApiError, mongo::traits::requests::{MongoRequest, FilterQuery, Namespaced}}, utils::mongo::{parse_docs, parse_field, parse_replacement, parse_update, parse_value}};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +18,11 @@ pub struct UpdateRequest {
     write_concern: Option<WriteConcern>,
     upsert: Option<bool>,
     array_filters: Option<Vec<Value>>,
+    collation: Option<Collation>,
+    hint: Option<Hint>,
+    #[serde(rename = "let")]
+    let_vars: Option<Value>,
+    comment: Option<Value>,
 }
 
 impl MongoRequest for UpdateRequest {
@@ -27,15 +32,15 @@ impl MongoRequest for UpdateRequest {
         &self.collection
     }
 
-    fn opts(&self) -> Self::OptionsType {
+    fn opts(&self) -> Result<Self::OptionsType, ApiError> {
         let mut update_one_opts = UpdateOptions::default();
 
         if let Some(bypass_document_validation) = self.bypass_document_validation {
-            update_one_opts.bypass_document_validation = Some(bypass_document_validation.clone());
+            update_one_opts.bypass_document_validation = Some(bypass_document_validation);
         }
 
         if let Some(upsert) = self.upsert {
-            update_one_opts.upsert = Some(upsert.clone());
+            update_one_opts.upsert = Some(upsert);
         }
 
         if let Some(write_concern) = &self.write_concern {
@@ -43,43 +48,75 @@ impl MongoRequest for UpdateRequest {
         }
 
         if let Some(array_filters) = &self.array_filters {
-            match parse_docs(array_filters) {
-                Ok(docs) => update_one_opts.array_filters = Some(docs),
-                Err(_) => update_one_opts.array_filters = None
-            }
+            update_one_opts.array_filters = Some(parse_docs("arrayFilters", array_filters)?);
         }
 
-        update_one_opts
+        if let Some(collation) = &self.collation {
+            update_one_opts.collation = Some(collation.clone());
+        }
+
+        if let Some(hint) = &self.hint {
+            update_one_opts.hint = Some(hint.clone());
+        }
+
+        if let Some(let_vars) = &self.let_vars {
+            update_one_opts.let_vars = Some(parse_field("let", let_vars)?);
+        }
+
+        if let Some(comment) = &self.comment {
+            update_one_opts.comment = Some(parse_value("comment", comment)?);
+        }
+
+        Ok(update_one_opts)
+    }
+}
+
+impl Namespaced for UpdateRequest {
+    fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    fn database(&self) -> Option<&str> {
+        self.database.as_deref()
     }
 }
 
-impl DocumentPayload for UpdateRequest {
-    type PayloadType = Document;
+// The same body is sent to `/updateOne`, `/updateMany` and `/replaceOne`, so its document is read as what the route expects
+impl UpdateRequest {
+    pub fn update(&self) -> Result<Document, ApiError> {
+        parse_update("document", &self.document)
+    }
 
-    fn payload(&self) -> Result<Self::PayloadType, bson::ser::Error> {
-        bson::to_document(&self.document)
+    pub fn replacement(&self) -> Result<Document, ApiError> {
+        parse_replacement("document", &self.document)
     }
 }
 
 impl FilterQuery for UpdateRequest {
-    fn filter(&self) -> Option<Result<Document, bson::ser::Error>> {
-        if let Some(json) = &self.filter {
-            Some(parse_filter(json))
-        } else {
-            None
-        }
+    fn filter(&self) -> Option<Result<Document, ApiError>> {
+        self.filter.as_ref().map(|json| parse_field("filter", json))
     }
 }
 
 pub struct UpdateOptionsWrapper(pub UpdateOptions);
 
-impl From<UpdateOptionsWrapper> for ReplaceOptions {
-    fn from(wrapper: UpdateOptionsWrapper) -> Self {
+// A replacement document has no array elements to filter, so array filters are rejected rather than dropped
+impl TryFrom<UpdateOptionsWrapper> for ReplaceOptions {
+    type Error = ApiError;
+
+    fn try_from(wrapper: UpdateOptionsWrapper) -> Result<Self, Self::Error> {
+        if wrapper.0.array_filters.is_some() {
+            return Err(ApiError::invalid_field("arrayFilters", "cannot be used with replaceOne"));
+        }
+
         let mut replace_opts = ReplaceOptions::default();
         replace_opts.bypass_document_validation = wrapper.0.bypass_document_validation;
         replace_opts.collation = wrapper.0.collation;
+        replace_opts.hint = wrapper.0.hint;
         replace_opts.upsert = wrapper.0.upsert;
         replace_opts.write_concern = wrapper.0.write_concern;
-        replace_opts
+        replace_opts.let_vars = wrapper.0.let_vars;
+        replace_opts.comment = wrapper.0.comment;
+        Ok(replace_opts)
     }
 }
\ No newline at end of file
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use mongodb::{options::{ChangeStreamOptions, FullDocumentBeforeChangeType, FullDocumentType}, bson::Document};
+
+use crate::{types::{errors::ApiError, mongo::traits::requests::DocumentPayload}, utils::mongo::parse_docs};
+
+/// Query string of `/watch`, e.g. `?collection=orders&pipeline=[...]`, since `EventSource` can only send a GET.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchQuery {
+    data_source: Option<String>,
+    database: Option<String>,
+    collection: Option<String>,
+    /// Extended JSON array of stages, URL encoded
+    pipeline: Option<String>,
+    /// Resume token, as sent in the SSE `id` of an event
+    resume_after: Option<String>,
+    full_document: Option<FullDocumentType>,
+    full_document_before_change: Option<FullDocumentBeforeChangeType>,
+    batch_size: Option<u32>,
+    #[serde(rename = "maxAwaitTimeMS")]
+    max_await_time_ms: Option<u64>,
+}
+
+// The collection is optional, without one the whole database is watched
+impl WatchQuery {
+    pub fn data_source(&self) -> Option<&str> {
+        self.data_source.as_deref()
+    }
+
+    pub fn database(&self) -> Option<&str> {
+        self.database.as_deref()
+    }
+
+    pub fn coll(&self) -> Option<&str> {
+        self.collection.as_deref()
+    }
+
+    pub fn resume_after(&self) -> Option<&str> {
+        self.resume_after.as_deref()
+    }
+
+    pub fn opts(&self) -> ChangeStreamOptions {
+        let mut change_stream_opts = ChangeStreamOptions::default();
+
+        if let Some(full_document) = &self.full_document {
+            change_stream_opts.full_document = Some(full_document.clone());
+        }
+
+        if let Some(full_document_before_change) = &self.full_document_before_change {
+            change_stream_opts.full_document_before_change = Some(full_document_before_change.clone());
+        }
+
+        if let Some(batch_size) = self.batch_size {
+            change_stream_opts.batch_size = Some(batch_size);
+        }
+
+        if let Some(max_await_time_ms) = self.max_await_time_ms {
+            change_stream_opts.max_await_time = Some(Duration::from_millis(max_await_time_ms));
+        }
+
+        change_stream_opts
+    }
+}
+
+impl DocumentPayload for WatchQuery {
+    type PayloadType = Vec<Document>;
+
+    fn payload(&self) -> Result<Self::PayloadType, ApiError> {
+        match &self.pipeline {
+            Some(pipeline) => {
+                let stages: Vec<Value> = serde_json::from_str(pipeline).map_err(|e| ApiError::invalid_field("pipeline", e))?;
+                parse_docs("pipeline", &stages)
+            },
+            None => Ok(vec![])
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Aggregated outcome of a bulk write, ids and errors are keyed by operation index. Write errors the server did not
+/// report, such as a lost connection, carry an `errorCode` instead of a server `code`. Operations listed in
+/// `writeConcernErrors` were applied but not acknowledged as requested, so their counts are missing.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkWriteResult {
+    pub inserted_count: u64,
+    pub matched_count: u64,
+    pub modified_count: u64,
+    pub deleted_count: u64,
+    pub upserted_count: u64,
+    pub inserted_ids: BTreeMap<usize, Value>,
+    pub upserted_ids: BTreeMap<usize, Value>,
+    pub write_errors: Vec<Value>,
+    pub write_concern_errors: Vec<Value>,
+}
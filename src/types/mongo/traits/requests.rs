@@ -1,19 +1,30 @@
-use mongodb::bson::{self, Document};
+use mongodb::bson::Document;
 
+use crate::types::errors::ApiError;
+
+/// A request against the collection it names. Requests not scoped to a collection, such as listing databases
+/// or starting a session, do not implement it and read their options through their own methods.
 pub trait MongoRequest {
     type OptionsType;
 
     fn coll(&self) -> &str;
 
-    fn opts(&self) -> Self::OptionsType;   
+    fn opts(&self) -> Result<Self::OptionsType, ApiError>;
 }
 
 pub trait FilterQuery {
-    fn filter(&self) -> Option<Result<Document, bson::ser::Error>>;
+    fn filter(&self) -> Option<Result<Document, ApiError>>;
 }
 
 pub trait DocumentPayload {
     type PayloadType;
 
-    fn payload(&self) -> Result<Self::PayloadType, bson::ser::Error>;
+    fn payload(&self) -> Result<Self::PayloadType, ApiError>;
+}
+
+/// Where a request runs, for operations that resolve their collection outside of `collection_mw`.
+pub trait Namespaced {
+    fn data_source(&self) -> Option<&str>;
+
+    fn database(&self) -> Option<&str>;
 }
\ No newline at end of file
@@ -0,0 +1,31 @@
+/// How a principal proved its identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    ApiKey,
+    Bearer
+}
+
+/// Who a request was authenticated as, attached to the request extensions by the authentication middleware.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    scheme: AuthScheme,
+    name: String
+}
+
+impl Principal {
+    pub fn new(scheme: AuthScheme, name: String) -> Self {
+        Principal { scheme, name }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name qualified by its scheme, so that an API key and a token subject sharing a name stay distinct.
+    pub fn id(&self) -> String {
+        match self.scheme {
+            AuthScheme::ApiKey => format!("apiKey:{}", self.name),
+            AuthScheme::Bearer => format!("bearer:{}", self.name)
+        }
+    }
+}
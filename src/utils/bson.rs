@@ -0,0 +1,101 @@
+use mongodb::bson::{self, Bson};
+use serde::{de::{self, IntoDeserializer, Visitor, value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer}}, Deserializer};
+
+/// Deserializes request types from a BSON body. Typed fields are read as with `bson::from_document`, while
+/// fields held as `serde_json::Value` receive canonical Extended JSON for the values plain JSON would lose
+/// (`$numberLong`, `$oid`, `$date`, `$binary`, ...), so `parse_field` turns them back into the original BSON.
+pub struct BsonBody(pub Bson);
+
+macro_rules! delegate_to_bson {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                bson::Deserializer::new(self.0).$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for BsonBody {
+    type Error = bson::de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Document(doc) => visitor.visit_map(MapDeserializer::new(doc.into_iter().map(|(key, value)| (key, BsonBody(value))))),
+            Bson::Array(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter().map(BsonBody))),
+            value @ (Bson::String(_) | Bson::Boolean(_) | Bson::Null | Bson::Int32(_)) => bson::Deserializer::new(value).deserialize_any(visitor),
+            Bson::Double(d) if d.is_finite() => visitor.visit_f64(d),
+            value => value.into_canonical_extjson().deserialize_any(visitor).map_err(de::Error::custom)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Array(_) => self.deserialize_any(visitor),
+            value => bson::Deserializer::new(value).deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Document(_) => self.deserialize_any(visitor),
+            value => bson::Deserializer::new(value).deserialize_map(visitor)
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Document(_) => self.deserialize_any(visitor),
+            value => bson::Deserializer::new(value).deserialize_struct(name, fields, visitor)
+        }
+    }
+
+    // Externally tagged enums such as `{"insertOne": {...}}` keep their payload wrapped as well
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Bson::Document(doc) if doc.len() == 1 => {
+                let entries = MapDeserializer::new(doc.into_iter().map(|(key, value)| (key, BsonBody(value))));
+                MapAccessDeserializer::new(entries).deserialize_enum(name, variants, visitor)
+            },
+            value => bson::Deserializer::new(value).deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        bson::Deserializer::new(self.0).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        bson::Deserializer::new(self.0).deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        bson::Deserializer::new(self.0).deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        bson::Deserializer::new(self.0).deserialize_tuple_struct(name, len, visitor)
+    }
+
+    delegate_to_bson! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_identifier deserialize_ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, bson::de::Error> for BsonBody {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,120 @@
+use std::net::SocketAddr;
+
+use axum::{async_trait, extract::{ConnectInfo, FromRequest, FromRequestParts, Query, rejection::JsonRejection}, http::{Request, request::Parts}, Json};
+use hyper::StatusCode;
+use mongodb::bson::Bson;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{middleware::mongo::BsonPayload, types::{errors::ApiError, principal::Principal}, utils::bson::BsonBody};
+
+/// Request body extractor that reports deserialization failures as an [`ApiError`].
+/// Reads JSON bodies, or the BSON body decoded by `bson_mw`.
+pub struct Payload<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Payload<T>
+where
+    T: DeserializeOwned,
+    Json<T>: FromRequest<S, B, Rejection = JsonRejection>,
+    S: Send + Sync,
+    B: Send + 'static
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(BsonPayload(doc)) = req.extensions().get::<BsonPayload>() {
+            return T::deserialize(BsonBody(Bson::Document(doc.clone())))
+                .map(Payload)
+                .map_err(|e| ApiError::InvalidBody { status: StatusCode::UNPROCESSABLE_ENTITY, message: e.to_string() });
+        }
+
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(Payload(value)),
+            Err(rejection) => Err(ApiError::InvalidBody { status: rejection.status(), message: rejection.body_text() })
+        }
+    }
+}
+
+/// Query string extractor that reports deserialization failures as an [`ApiError`], for routes without a JSON body.
+pub struct Params<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Params<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Params(value)),
+            Err(rejection) => Err(ApiError::InvalidBody { status: rejection.status(), message: rejection.body_text() })
+        }
+    }
+}
+
+pub const SESSION_TOKEN_HEADER: &str = "sessionToken";
+
+/// Identity that server-held sessions and cursors are bound to: the authenticated principal, or the peer
+/// address of the connection when authentication is disabled.
+pub struct Caller(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Caller
+where
+    S: Send + Sync
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(principal) = parts.extensions.get::<Principal>() {
+            return Ok(Caller(principal.id()));
+        }
+
+        match parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => Ok(Caller(addr.ip().to_string())),
+            None => Err(ApiError::Unauthorized("Unable to identify the caller".to_string()))
+        }
+    }
+}
+
+/// Claims of the verified `Authorization: Bearer` token, attached by `auth_mw`. Requests authenticated otherwise
+/// are rejected with a `401`.
+#[derive(Debug, Clone)]
+pub struct Claims(pub Map<String, Value>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Claims>() {
+            Some(claims) => Ok(claims.clone()),
+            None => Err(ApiError::Unauthorized("Requires an Authorization: Bearer token".to_string()))
+        }
+    }
+}
+
+/// Token of an interactive session, read from the `sessionToken` header.
+pub struct SessionToken(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionToken
+where
+    S: Send + Sync
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.headers.get(SESSION_TOKEN_HEADER).map(|val| val.to_str()) {
+            Some(Ok(token)) => Ok(SessionToken(token.to_string())),
+            Some(Err(_)) => Err(ApiError::BadRequest(format!("Invalid {} header", SESSION_TOKEN_HEADER))),
+            None => Err(ApiError::BadRequest(format!("Missing {} header", SESSION_TOKEN_HEADER)))
+        }
+    }
+}
@@ -1,16 +1,21 @@
-use futures::StreamExt;
-use mongodb::{bson::{Document, self}, Cursor};
-use serde_json::{Value, json};
+use std::io;
 
-pub async fn docs_as_json(cursor: Cursor<Document>) -> Result<Value, mongodb::error::Error> {
-    let mut result: Vec<Document> = vec![];
+use axum::{body::StreamBody, http::header::CONTENT_TYPE, response::{IntoResponse, Response}};
+use futures::{stream, StreamExt};
+use mongodb::{bson::{self, Document, Bson, extjson}, change_stream::event::ResumeToken, Cursor};
+use serde_json::Value;
+
+use crate::{middleware::headers::{ExtJsonFormat, BSON, NDJSON}, types::{errors::ApiError, mongo::traits::requests::FilterQuery}};
+
+pub async fn docs_as_json(cursor: Cursor<Document>, format: ExtJsonFormat) -> Result<Value, mongodb::error::Error> {
+    let mut result: Vec<Value> = vec![];
 
     let collected: Vec<Result<Document, mongodb::error::Error>> = cursor.collect().await;
 
     for doc in collected {
         match doc {
             Ok(d) => {
-                result.push(d);
+                result.push(doc_as_json(d, format));
             },
             Err(e) => {
                 return Err(e);
@@ -18,25 +23,140 @@ pub async fn docs_as_json(cursor: Cursor<Document>) -> Result<Value, mongodb::er
         }
     }
 
-    Ok(json!(result))
+    Ok(Value::Array(result))
 }
 
-pub fn parse_docs(json_list: &Vec<Value>) -> Result<Vec<Document>, bson::ser::Error> {
+/// Streams the cursor as newline-delimited JSON without buffering it. A failure mid-stream is written as a
+/// final error line and the body is then cut off, so clients see an incomplete response rather than a short one.
+pub fn docs_as_ndjson(cursor: Cursor<Document>, format: ExtJsonFormat) -> Response {
+    let lines = stream::unfold(Some(Ok(cursor)), move |state| async move {
+        match state? {
+            Ok(mut cursor) => match cursor.next().await {
+                Some(Ok(doc)) => Some((Ok(json_line(doc_as_json(doc, format))), Some(Ok(cursor)))),
+                Some(Err(err)) => {
+                    let (_, body) = ApiError::from(err).into_parts();
+                    Some((Ok(json_line(body)), Some(Err(()))))
+                },
+                None => None
+            },
+            Err(()) => Some((Err(io::Error::other("cursor failed mid-stream")), None))
+        }
+    });
+
+    ([(CONTENT_TYPE, NDJSON)], StreamBody::new(lines)).into_response()
+}
+
+/// Streams the cursor as a sequence of BSON documents. A failure mid-stream cuts the body off after the
+/// last complete document.
+pub fn docs_as_bson(cursor: Cursor<Document>) -> Response {
+    let docs = cursor.map(|doc| {
+        let mut bytes = Vec::new();
+        doc.map_err(io::Error::other)?.to_writer(&mut bytes).map_err(io::Error::other)?;
+        Ok::<_, io::Error>(bytes)
+    });
+
+    ([(CONTENT_TYPE, BSON)], StreamBody::new(docs)).into_response()
+}
+
+/// Responds with a single BSON document.
+pub fn doc_as_bson(doc: Document) -> Result<Response, ApiError> {
+    let mut bytes = Vec::new();
+    doc.to_writer(&mut bytes).map_err(mongodb::error::Error::from)?;
+
+    Ok(([(CONTENT_TYPE, BSON)], bytes).into_response())
+}
+
+fn json_line(value: Value) -> String {
+    let mut line = value.to_string();
+    line.push('\n');
+    line
+}
+
+pub fn doc_as_json(doc: Document, format: ExtJsonFormat) -> Value {
+    bson_as_json(Bson::Document(doc), format)
+}
+
+pub fn bson_as_json(value: Bson, format: ExtJsonFormat) -> Value {
+    match format {
+        ExtJsonFormat::Canonical => value.into_canonical_extjson(),
+        ExtJsonFormat::Relaxed => value.into_relaxed_extjson()
+    }
+}
+
+/// Parses a list of Extended JSON documents, naming the failing element as `field.index` on error.
+pub fn parse_docs(field: &str, json_list: &[Value]) -> Result<Vec<Document>, ApiError> {
     if json_list.is_empty() {
         let result: Vec<Document> = vec![];
         return Ok(result);
     }
 
     json_list
-        .clone()
-        .into_iter()
-        .map(|json| bson::to_document(&json))
+        .iter()
+        .enumerate()
+        .map(|(i, json)| parse_field(&format!("{}.{}", field, i), json))
         .collect()
 }
 
-pub fn parse_filter(json: &Value) -> Result<Document, bson::ser::Error> {
-    match bson::to_document(json) {
-        Ok(doc) => Ok(doc),
-        Err(e) => Err(e.into()) 
+/// Parses a request field as an Extended JSON document, naming the field on error.
+pub fn parse_field(field: &str, json: &Value) -> Result<Document, ApiError> {
+    parse_filter(json).map_err(|e| ApiError::invalid_field(field, e))
+}
+
+/// Parses an update document, which may only hold update operators such as `$set`, naming the field on error.
+pub fn parse_update(field: &str, json: &Value) -> Result<Document, ApiError> {
+    let update = parse_field(field, json)?;
+
+    if update.is_empty() {
+        return Err(ApiError::invalid_field(field, "update document must not be empty"));
+    }
+
+    match update.keys().find(|key| !key.starts_with('$')) {
+        Some(key) => Err(ApiError::invalid_field(field, format!("update document must only contain update operators, found '{}'", key))),
+        None => Ok(update)
+    }
+}
+
+/// Parses a replacement document, which may not hold update operators, naming the field on error.
+pub fn parse_replacement(field: &str, json: &Value) -> Result<Document, ApiError> {
+    let replacement = parse_field(field, json)?;
+
+    match replacement.keys().find(|key| key.starts_with('$')) {
+        Some(key) => Err(ApiError::invalid_field(field, format!("replacement document must not contain update operators, found '{}'", key))),
+        None => Ok(replacement)
+    }
+}
+
+/// Parses a request field as any Extended JSON value, naming the field on error.
+pub fn parse_value(field: &str, json: &Value) -> Result<Bson, ApiError> {
+    Bson::try_from(json.clone()).map_err(|e| ApiError::invalid_field(field, e))
+}
+
+/// Parses a JSON value as Extended JSON, so `$oid`, `$date`, `$numberLong` etc. become their BSON types.
+pub fn parse_filter(json: &Value) -> Result<Document, extjson::de::Error> {
+    match Bson::try_from(json.clone())? {
+        Bson::Document(doc) => Ok(doc),
+        other => Err(extjson::de::Error::DeserializationError {
+            message: format!("expected a document, found {:?}", other.element_type())
+        })
+    }
+}
+
+// Write operations require a filter, an empty one must be sent explicitly
+pub fn required_filter<R: FilterQuery>(body: &R) -> Result<Document, ApiError> {
+    match body.filter() {
+        Some(filter) => filter,
+        None => Err(ApiError::missing_field("filter"))
     }
+}
+/// Serializes a resume token as a single line of Extended JSON, e.g. `{"_data":"8264..."}`.
+pub fn resume_token_id(token: Bson) -> String {
+    token.into_relaxed_extjson().to_string()
+}
+
+/// Parses a resume token produced by [`resume_token_id`], naming `field` on error.
+pub fn parse_resume_token(field: &str, id: &str) -> Result<ResumeToken, ApiError> {
+    let json: Value = serde_json::from_str(id).map_err(|e| ApiError::invalid_field(field, e))?;
+    let token = parse_field(field, &json)?;
+
+    bson::from_document(token).map_err(|e| ApiError::invalid_field(field, e))
 }
\ No newline at end of file
@@ -0,0 +1,454 @@
+use std::{env, fmt, sync::Arc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::{bson::{doc, Bson, Document}, options::FindOptions, Collection};
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::types::errors::ApiError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAC_LEN: usize = 32;
+// Pages are read into memory as a whole
+const MAX_PAGE_SIZE: i64 = 1000;
+
+// `$type` aliases by where their values sort, see https://www.mongodb.com/docs/manual/reference/bson-type-comparison-order/
+const TYPE_ORDER: [&[&str]; 13] = [
+    &["minKey"],
+    &["null"],
+    &["double", "int", "long", "decimal"],
+    &["string", "symbol"],
+    &["object"],
+    &["array"],
+    &["binData"],
+    &["objectId"],
+    &["bool"],
+    &["date"],
+    &["timestamp"],
+    &["regex"],
+    &["maxKey"]
+];
+const NULL_RANK: usize = 1;
+
+/// Signs the `pageToken`s of keyset pagination with `PAGE_TOKEN_SECRET`. Without it a random key is
+/// generated at startup, so tokens do not survive a restart or work across instances.
+#[derive(Clone)]
+pub struct PageSigner {
+    key: Arc<Vec<u8>>
+}
+
+// Keeps the key out of the state's Debug output
+impl fmt::Debug for PageSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageSigner").finish_non_exhaustive()
+    }
+}
+
+impl PageSigner {
+    pub fn from_env() -> Self {
+        let key = match env::var("PAGE_TOKEN_SECRET") {
+            Ok(secret) => secret.into_bytes(),
+            Err(_) => {
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+
+        PageSigner { key: Arc::new(key) }
+    }
+
+    /// Encodes the sort spec and the sort values of the last document of a page as `base64url(bson || hmac)`.
+    fn sign(&self, sort: &Document, last: Vec<Bson>) -> Result<String, ApiError> {
+        let mut token = Vec::new();
+        doc! { "sort": sort.clone(), "after": last }
+            .to_writer(&mut token)
+            .map_err(|e| ApiError::invalid_field("sort", e))?;
+
+        let mac = self.mac(&token).finalize().into_bytes();
+        token.extend_from_slice(&mac);
+
+        Ok(URL_SAFE_NO_PAD.encode(token))
+    }
+
+    /// Checks the token's signature and that it was issued for `sort`, returning the sort values to continue after.
+    fn verify(&self, token: &str, sort: &Document) -> Result<Vec<Bson>, ApiError> {
+        let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|e| ApiError::invalid_field("pageToken", e))?;
+
+        if bytes.len() < MAC_LEN {
+            return Err(ApiError::invalid_field("pageToken", "token is truncated"));
+        }
+
+        let (payload, mac) = bytes.split_at(bytes.len() - MAC_LEN);
+        self.mac(payload).verify_slice(mac).map_err(|_| ApiError::invalid_field("pageToken", "signature does not match"))?;
+
+        let token = Document::from_reader(payload).map_err(|e| ApiError::invalid_field("pageToken", e))?;
+
+        if token.get_document("sort").ok() != Some(sort) {
+            return Err(ApiError::invalid_field("pageToken", "token was issued for a different sort"));
+        }
+
+        token.get_array("after").cloned().map_err(|e| ApiError::invalid_field("pageToken", e))
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+/// Reads one page of at most `page_size` documents in `sort` order, starting after the position encoded in
+/// `page_token`, and returns it with the token of the next page when there is one.
+pub async fn find_page(coll: &Collection<Document>, filter: Option<Document>, mut opts: FindOptions, page_size: i64, page_token: Option<&str>, signer: &PageSigner) -> Result<(Vec<Document>, Option<String>), ApiError> {
+    let limit = page_limit(page_size)?;
+
+    if opts.skip.is_some() {
+        return Err(ApiError::invalid_field("skip", "cannot be combined with pageSize"));
+    }
+
+    if opts.limit.is_some() {
+        return Err(ApiError::invalid_field("limit", "cannot be combined with pageSize"));
+    }
+
+    let sort = page_sort(opts.sort.take())?;
+
+    if let Some(projection) = &opts.projection {
+        check_projection(projection, &sort)?;
+    }
+
+    let mut filter = filter.unwrap_or_default();
+
+    if let Some(token) = page_token {
+        let last = signer.verify(token, &sort)?;
+        filter = doc! { "$and": [filter, after(&sort, last)?] };
+    }
+
+    opts.sort = Some(sort.clone());
+    opts.limit = Some(limit);
+
+    let mut docs: Vec<Document> = coll.find(filter, opts).await?.try_collect().await?;
+
+    if docs.len() as i64 <= page_size {
+        return Ok((docs, None));
+    }
+
+    docs.truncate(page_size as usize);
+    let next_page_token = match docs.last() {
+        Some(last) => Some(signer.sign(&sort, sort_values(&sort, last))?),
+        None => None
+    };
+
+    Ok((docs, next_page_token))
+}
+
+// One extra document tells whether there is a next page
+fn page_limit(page_size: i64) -> Result<i64, ApiError> {
+    if page_size <= 0 {
+        return Err(ApiError::invalid_field("pageSize", "must be positive"));
+    }
+
+    if page_size > MAX_PAGE_SIZE {
+        return Err(ApiError::invalid_field("pageSize", format!("must be at most {}", MAX_PAGE_SIZE)));
+    }
+
+    Ok(page_size + 1)
+}
+
+// Keys must be plain 1 or -1 directions, normalized so equal specs compare equal, and `_id` breaks ties
+fn page_sort(sort: Option<Document>) -> Result<Document, ApiError> {
+    let mut page_sort = Document::new();
+
+    for (key, direction) in sort.unwrap_or_default() {
+        let direction = match direction {
+            Bson::Int32(d) => d as i64,
+            Bson::Int64(d) => d,
+            Bson::Double(d) if d.fract() == 0.0 => d as i64,
+            _ => 0
+        };
+
+        if direction != 1 && direction != -1 {
+            return Err(ApiError::invalid_field(format!("sort.{}", key), "must be 1 or -1 when paging"));
+        }
+
+        page_sort.insert(key, direction as i32);
+    }
+
+    if !page_sort.contains_key("_id") {
+        page_sort.insert("_id", 1);
+    }
+
+    Ok(page_sort)
+}
+
+// The next page starts after the sort values of the last document as returned, so the projection has to keep
+// every sort key unchanged, `_id` included
+fn check_projection(projection: &Document, sort: &Document) -> Result<(), ApiError> {
+    let inclusive = projection.iter().any(|(key, value)| key != "_id" && !is_exclusion(value));
+
+    for key in sort.keys() {
+        // An entry for a parent path decides for the key, one for a nested path changes the key's value
+        let entries: Vec<(&String, &Bson)> = projection
+            .iter()
+            .filter(|(path, _)| *path == key || key.starts_with(&format!("{}.", path)) || path.starts_with(&format!("{}.", key)))
+            .collect();
+
+        let kept = match entries.as_slice() {
+            [] => key == "_id" || !inclusive,
+            [(path, value)] if *path == key || key.starts_with(&format!("{}.", path)) => is_inclusion(value),
+            _ => false
+        };
+
+        if !kept {
+            return Err(ApiError::invalid_field("projection", format!("must keep the sort key '{}' unchanged when paging", key)));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_exclusion(value: &Bson) -> bool {
+    match value {
+        Bson::Boolean(flag) => !flag,
+        Bson::Int32(n) => *n == 0,
+        Bson::Int64(n) => *n == 0,
+        Bson::Double(n) => *n == 0.0,
+        _ => false
+    }
+}
+
+// Expressions, `$slice` and `$elemMatch` are neither, they change the value
+fn is_inclusion(value: &Bson) -> bool {
+    matches!(value, Bson::Boolean(_) | Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_)) && !is_exclusion(value)
+}
+
+// Documents strictly after `last` in sort order:
+// {$or: [{a: {$gt: va}}, {a: va, b: {$gt: vb}}, ...]}, with $lt for descending keys
+fn after(sort: &Document, last: Vec<Bson>) -> Result<Document, ApiError> {
+    if last.len() != sort.len() {
+        return Err(ApiError::invalid_field("pageToken", "token was issued for a different sort"));
+    }
+
+    let keys: Vec<(&String, &Bson)> = sort.iter().collect();
+    let mut branches = Vec::new();
+
+    for (i, ((key, direction), value)) in keys.iter().zip(&last).enumerate() {
+        for condition in beyond(value, **direction == Bson::Int32(-1)) {
+            let mut branch = Document::new();
+
+            for ((prev_key, _), prev_value) in keys[..i].iter().zip(&last) {
+                branch.insert(prev_key.as_str(), prev_value.clone());
+            }
+
+            branch.insert(key.as_str(), condition);
+            branches.push(branch);
+        }
+    }
+
+    Ok(doc! { "$or": branches })
+}
+
+// Conditions on a key matching the values that sort strictly beyond `value`. `$gt` and `$lt` only compare values
+// of the same type, so values of the types sorting beyond it are matched by `$type`. Missing values sort as null,
+// which `{$gt: null}` never matches but `{$eq: null}` does.
+fn beyond(value: &Bson, descending: bool) -> Vec<Bson> {
+    let rank = TYPE_ORDER
+        .iter()
+        .position(|aliases| aliases.contains(&type_alias(value)))
+        .unwrap_or(TYPE_ORDER.len());
+
+    let mut conditions = Vec::new();
+
+    if rank != NULL_RANK {
+        let op = if descending { "$lt" } else { "$gt" };
+        conditions.push(Bson::Document(doc! { op: value.clone() }));
+    }
+
+    let beyond_rank = |i: usize| if descending { i < rank } else { i > rank };
+
+    let types: Vec<&str> = (0..TYPE_ORDER.len())
+        .filter(|i| *i != NULL_RANK && beyond_rank(*i))
+        .flat_map(|i| TYPE_ORDER[i].iter().copied())
+        .collect();
+
+    if !types.is_empty() {
+        conditions.push(Bson::Document(doc! { "$type": types }));
+    }
+
+    if beyond_rank(NULL_RANK) {
+        conditions.push(Bson::Null);
+    }
+
+    conditions
+}
+
+fn type_alias(value: &Bson) -> &'static str {
+    match value {
+        Bson::MinKey => "minKey",
+        Bson::Null | Bson::Undefined => "null",
+        Bson::Double(_) => "double",
+        Bson::Int32(_) => "int",
+        Bson::Int64(_) => "long",
+        Bson::Decimal128(_) => "decimal",
+        Bson::String(_) | Bson::Symbol(_) => "string",
+        Bson::Document(_) => "object",
+        Bson::Array(_) => "array",
+        Bson::Binary(_) => "binData",
+        Bson::ObjectId(_) => "objectId",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Timestamp(_) => "timestamp",
+        Bson::RegularExpression(_) => "regex",
+        Bson::MaxKey => "maxKey",
+        _ => "javascript"
+    }
+}
+
+// Values of the sort keys in `doc`, with missing values as null
+fn sort_values(sort: &Document, doc: &Document) -> Vec<Bson> {
+    sort.keys().map(|key| lookup(doc, key).cloned().unwrap_or(Bson::Null)).collect()
+}
+
+// Follows a dotted path through embedded documents
+fn lookup<'a>(doc: &'a Document, path: &str) -> Option<&'a Bson> {
+    match path.split_once('.') {
+        Some((head, rest)) => match doc.get(head)? {
+            Bson::Document(inner) => lookup(inner, rest),
+            _ => None
+        },
+        None => doc.get(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, Bson};
+
+    use super::*;
+
+    fn signer(key: &[u8]) -> PageSigner {
+        PageSigner { key: Arc::new(key.to_vec()) }
+    }
+
+    #[test]
+    fn round_trips_tokens() {
+        let signer = signer(b"secret");
+        let sort = doc! { "a": 1, "_id": 1 };
+        let last = vec![Bson::Int32(5), Bson::String("x".to_string())];
+
+        let token = signer.sign(&sort, last.clone()).unwrap();
+
+        assert_eq!(signer.verify(&token, &sort).unwrap(), last);
+    }
+
+    #[test]
+    fn rejects_tampered_tokens() {
+        let sort = doc! { "a": 1, "_id": 1 };
+        let token = signer(b"secret").sign(&sort, vec![Bson::Int32(5), Bson::Int32(1)]).unwrap();
+
+        let mut bytes = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        bytes[10] ^= 1;
+        let tampered = URL_SAFE_NO_PAD.encode(bytes);
+
+        assert!(signer(b"secret").verify(&tampered, &sort).is_err());
+        assert!(signer(b"other").verify(&token, &sort).is_err());
+        assert!(signer(b"secret").verify(&token[..8], &sort).is_err());
+        assert!(signer(b"secret").verify(&token, &doc! { "a": -1, "_id": 1 }).is_err());
+    }
+
+    #[test]
+    fn normalizes_sorts() {
+        assert_eq!(page_sort(Some(doc! { "a": -1.0 })).unwrap(), doc! { "a": -1, "_id": 1 });
+        assert_eq!(page_sort(Some(doc! { "_id": -1 })).unwrap(), doc! { "_id": -1 });
+        assert!(page_sort(Some(doc! { "a": { "$meta": "textScore" } })).is_err());
+    }
+
+    const AFTER_NUMBERS: [&str; 11] = ["string", "symbol", "object", "array", "binData", "objectId", "bool", "date", "timestamp", "regex", "maxKey"];
+    const AFTER_NULL: [&str; 15] = ["double", "int", "long", "decimal", "string", "symbol", "object", "array", "binData", "objectId", "bool", "date", "timestamp", "regex", "maxKey"];
+
+    #[test]
+    fn limits_page_size() {
+        assert_eq!(page_limit(1).unwrap(), 2);
+        assert_eq!(page_limit(MAX_PAGE_SIZE).unwrap(), MAX_PAGE_SIZE + 1);
+        assert!(page_limit(0).is_err());
+        assert!(page_limit(MAX_PAGE_SIZE + 1).is_err());
+        assert!(page_limit(i64::MAX).is_err());
+    }
+
+    #[test]
+    fn filters_after_the_last_document() {
+        let sort = doc! { "a": 1, "b": -1, "_id": 1 };
+        let last = vec![Bson::Int32(1), Bson::Int32(2), Bson::Int32(3)];
+
+        assert_eq!(after(&sort, last).unwrap(), doc! { "$or": [
+            { "a": { "$gt": 1 } },
+            { "a": { "$type": AFTER_NUMBERS.to_vec() } },
+            { "a": 1, "b": { "$lt": 2 } },
+            { "a": 1, "b": { "$type": ["minKey"] } },
+            { "a": 1, "b": null },
+            { "a": 1, "b": 2, "_id": { "$gt": 3 } },
+            { "a": 1, "b": 2, "_id": { "$type": AFTER_NUMBERS.to_vec() } }
+        ] });
+
+        assert!(after(&sort, vec![Bson::Int32(1)]).is_err());
+    }
+
+    #[test]
+    fn continues_after_missing_sort_values() {
+        let page = [doc! { "_id": 1, "a": 5 }, doc! { "_id": 2 }];
+        let last = sort_values(&doc! { "a": 1, "_id": 1 }, &page[1]);
+        assert_eq!(last, vec![Bson::Null, Bson::Int32(2)]);
+
+        assert_eq!(after(&doc! { "a": 1, "_id": 1 }, last.clone()).unwrap(), doc! { "$or": [
+            { "a": { "$type": AFTER_NULL.to_vec() } },
+            { "a": null, "_id": { "$gt": 2 } },
+            { "a": null, "_id": { "$type": AFTER_NUMBERS.to_vec() } }
+        ] });
+
+        assert_eq!(after(&doc! { "a": -1, "_id": 1 }, last).unwrap(), doc! { "$or": [
+            { "a": { "$type": ["minKey"] } },
+            { "a": null, "_id": { "$gt": 2 } },
+            { "a": null, "_id": { "$type": AFTER_NUMBERS.to_vec() } }
+        ] });
+    }
+
+    #[test]
+    fn continues_across_types() {
+        assert_eq!(beyond(&Bson::String("x".to_string()), false), vec![
+            Bson::Document(doc! { "$gt": "x" }),
+            Bson::Document(doc! { "$type": ["object", "array", "binData", "objectId", "bool", "date", "timestamp", "regex", "maxKey"] })
+        ]);
+
+        assert_eq!(beyond(&Bson::String("x".to_string()), true), vec![
+            Bson::Document(doc! { "$lt": "x" }),
+            Bson::Document(doc! { "$type": ["minKey", "double", "int", "long", "decimal"] }),
+            Bson::Null
+        ]);
+    }
+
+    #[test]
+    fn reads_sort_values() {
+        let sort = doc! { "a.b": 1, "c": 1, "_id": 1 };
+
+        assert_eq!(sort_values(&sort, &doc! { "_id": 7, "a": { "b": "x" } }), vec![Bson::String("x".to_string()), Bson::Null, Bson::Int32(7)]);
+    }
+
+    #[test]
+    fn requires_projections_to_keep_sort_keys() {
+        let sort = doc! { "a.b": 1, "_id": 1 };
+
+        assert!(check_projection(&doc! { "a": 1 }, &sort).is_ok());
+        assert!(check_projection(&doc! { "a.b": 1, "c": 1 }, &sort).is_ok());
+        assert!(check_projection(&doc! { "c": 0 }, &sort).is_ok());
+
+        assert!(check_projection(&doc! { "c": 1 }, &sort).is_err());
+        assert!(check_projection(&doc! { "a": 1, "_id": 0 }, &sort).is_err());
+        assert!(check_projection(&doc! { "a": 0 }, &sort).is_err());
+        assert!(check_projection(&doc! { "a.b.c": 1 }, &sort).is_err());
+        assert!(check_projection(&doc! { "a": { "$slice": 1 } }, &sort).is_err());
+        assert!(check_projection(&doc! { "a.b": "$c" }, &sort).is_err());
+    }
+}
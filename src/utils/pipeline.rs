@@ -0,0 +1,203 @@
+use std::env;
+
+use mongodb::bson::{Bson, Document};
+
+use crate::types::errors::ApiError;
+
+/// Limits what aggregation pipelines may reach beyond the collection they run on. `$out` and `$merge` may only
+/// write to the listed write targets, and lookups into another database are rejected unless cross database
+/// lookups are allowed. Part of the `QueryRules` of a namespace, defaulting to `AGGREGATE_WRITE_TARGETS`
+/// and `AGGREGATE_CROSS_DB_LOOKUP`.
+#[derive(Debug, Clone, Default)]
+pub struct PipelinePolicy {
+    write_targets: Vec<String>,
+    cross_db_lookup: bool
+}
+
+impl PipelinePolicy {
+    pub fn from_env() -> Self {
+        // AGGREGATE_WRITE_TARGETS is a comma separated list of `db.collection`, or `db.*` for a whole database
+        let write_targets = env::var("AGGREGATE_WRITE_TARGETS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|target| target.trim().to_string())
+            .filter(|target| !target.is_empty())
+            .collect();
+
+        let cross_db_lookup = env::var("AGGREGATE_CROSS_DB_LOOKUP")
+            .map(|val| val.parse::<bool>().expect("Error: Failed to parse AGGREGATE_CROSS_DB_LOOKUP from environment"))
+            .unwrap_or(false);
+
+        PipelinePolicy { write_targets, cross_db_lookup }
+    }
+
+    pub fn with_overrides(&self, write_targets: Option<Vec<String>>, cross_db_lookup: Option<bool>) -> Self {
+        PipelinePolicy {
+            write_targets: write_targets.unwrap_or_else(|| self.write_targets.clone()),
+            cross_db_lookup: cross_db_lookup.unwrap_or(self.cross_db_lookup)
+        }
+    }
+
+    /// Checks every stage of a pipeline run against `db`, including the pipelines nested in lookups and facets.
+    /// `is_allowed` is the database allowlist, which every database named by the pipeline must pass as well.
+    pub fn check(&self, db: &str, pipeline: &[Document], is_allowed: &dyn Fn(&str) -> bool) -> Result<(), ApiError> {
+        self.check_stages("pipeline", db, pipeline, is_allowed)
+    }
+
+    fn check_stages(&self, field: &str, db: &str, pipeline: &[Document], is_allowed: &dyn Fn(&str) -> bool) -> Result<(), ApiError> {
+        for (i, stage) in pipeline.iter().enumerate() {
+            for (name, spec) in stage {
+                let field = format!("{}.{}.{}", field, i, name);
+
+                match (name.as_str(), spec) {
+                    ("$out", target) => {
+                        self.check_write(name, namespace(&field, db, target)?, is_allowed)?;
+                    },
+                    ("$merge", Bson::Document(merge)) => {
+                        let into = merge.get("into").ok_or_else(|| ApiError::missing_field(format!("{}.into", field)))?;
+                        self.check_write(name, namespace(&format!("{}.into", field), db, into)?, is_allowed)?;
+                    },
+                    ("$merge", target) => {
+                        self.check_write(name, namespace(&field, db, target)?, is_allowed)?;
+                    },
+                    ("$lookup" | "$graphLookup", Bson::Document(lookup)) => {
+                        if let Some(from) = lookup.get("from") {
+                            self.check_read(name, db, namespace(&format!("{}.from", field), db, from)?, is_allowed)?;
+                        }
+                        self.check_nested(&field, db, lookup, is_allowed)?;
+                    },
+                    ("$unionWith", Bson::Document(union)) => {
+                        self.check_read(name, db, namespace(&field, db, spec)?, is_allowed)?;
+                        self.check_nested(&field, db, union, is_allowed)?;
+                    },
+                    ("$facet", Bson::Document(facets)) => {
+                        for (facet, stages) in facets {
+                            self.check_stages(&format!("{}.{}", field, facet), db, &stage_docs(&format!("{}.{}", field, facet), stages)?, is_allowed)?;
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_nested(&self, field: &str, db: &str, spec: &Document, is_allowed: &dyn Fn(&str) -> bool) -> Result<(), ApiError> {
+        match spec.get("pipeline") {
+            Some(stages) => self.check_stages(&format!("{}.pipeline", field), db, &stage_docs(&format!("{}.pipeline", field), stages)?, is_allowed),
+            None => Ok(())
+        }
+    }
+
+    fn check_write(&self, stage: &str, (db, coll): (String, String), is_allowed: &dyn Fn(&str) -> bool) -> Result<(), ApiError> {
+        let allowed = is_allowed(&db) && self.write_targets.iter().any(|target| *target == format!("{}.{}", db, coll) || *target == format!("{}.*", db));
+
+        if !allowed {
+            return Err(ApiError::Forbidden(format!("{} may not write to {}.{}", stage, db, coll)));
+        }
+
+        Ok(())
+    }
+
+    fn check_read(&self, stage: &str, db: &str, (target_db, _): (String, String), is_allowed: &dyn Fn(&str) -> bool) -> Result<(), ApiError> {
+        if target_db != db && !(self.cross_db_lookup && is_allowed(&target_db)) {
+            return Err(ApiError::Forbidden(format!("{} may not read from database {}", stage, target_db)));
+        }
+
+        Ok(())
+    }
+}
+
+// Stages name their collection as `"coll"` or `{"db": ..., "coll": ...}`, in the pipeline's database unless it names another
+fn namespace(field: &str, db: &str, spec: &Bson) -> Result<(String, String), ApiError> {
+    match spec {
+        Bson::String(coll) => Ok((db.to_string(), coll.clone())),
+        Bson::Document(ns) => {
+            let db = match ns.get("db") {
+                Some(Bson::String(name)) => name.clone(),
+                Some(_) => return Err(ApiError::invalid_field(format!("{}.db", field), "expected a string")),
+                None => db.to_string()
+            };
+
+            match ns.get("coll") {
+                Some(Bson::String(coll)) => Ok((db, coll.clone())),
+                Some(_) => Err(ApiError::invalid_field(format!("{}.coll", field), "expected a string")),
+                None => Err(ApiError::missing_field(format!("{}.coll", field)))
+            }
+        },
+        _ => Err(ApiError::invalid_field(field, "expected a collection name or {db, coll}"))
+    }
+}
+
+fn stage_docs(field: &str, stages: &Bson) -> Result<Vec<Document>, ApiError> {
+    match stages {
+        Bson::Array(stages) => stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| match stage {
+                Bson::Document(stage) => Ok(stage.clone()),
+                _ => Err(ApiError::invalid_field(format!("{}.{}", field, i), "expected a document"))
+            })
+            .collect(),
+        _ => Err(ApiError::invalid_field(field, "expected an array of stages"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::*;
+
+    fn policy(write_targets: &[&str], cross_db_lookup: bool) -> PipelinePolicy {
+        PipelinePolicy::default().with_overrides(Some(write_targets.iter().map(|target| target.to_string()).collect()), Some(cross_db_lookup))
+    }
+
+    fn check(policy: &PipelinePolicy, pipeline: &[Document]) -> Result<(), ApiError> {
+        policy.check("app", pipeline, &|db| db == "app" || db == "reports")
+    }
+
+    #[test]
+    fn limits_write_targets() {
+        let policy = policy(&["app.out", "reports.*"], false);
+
+        assert!(check(&policy, &[doc! { "$out": "out" }]).is_ok());
+        assert!(check(&policy, &[doc! { "$merge": { "into": { "db": "reports", "coll": "daily" } } }]).is_ok());
+        assert!(matches!(check(&policy, &[doc! { "$out": "other" }]), Err(ApiError::Forbidden(_))));
+        assert!(matches!(check(&policy, &[doc! { "$merge": "other" }]), Err(ApiError::Forbidden(_))));
+        assert!(matches!(check(&PipelinePolicy::default(), &[doc! { "$out": "out" }]), Err(ApiError::Forbidden(_))));
+    }
+
+    #[test]
+    fn rejects_cross_db_lookups() {
+        let lookup = [doc! { "$lookup": { "from": { "db": "reports", "coll": "daily" }, "as": "daily" } }];
+
+        assert!(matches!(check(&policy(&[], false), &lookup), Err(ApiError::Forbidden(_))));
+        assert!(check(&policy(&[], true), &lookup).is_ok());
+
+        let lookup = doc! { "$lookup": { "from": { "db": "admin", "coll": "system.users" }, "as": "users" } };
+        assert!(matches!(check(&policy(&[], true), &[lookup]), Err(ApiError::Forbidden(_))));
+    }
+
+    #[test]
+    fn checks_lookups_nested_in_facets() {
+        let pipeline = [doc! { "$facet": { "a": [{ "$match": {} }], "b": [{ "$lookup": { "from": { "db": "reports", "coll": "daily" }, "as": "daily" } }] } }];
+
+        assert!(matches!(check(&policy(&[], false), &pipeline), Err(ApiError::Forbidden(_))));
+        assert!(check(&policy(&[], true), &pipeline).is_ok());
+    }
+
+    #[test]
+    fn checks_lookups_nested_in_union_with() {
+        let nested = [doc! { "$unionWith": { "coll": "archive", "pipeline": [{ "$lookup": { "from": { "db": "reports", "coll": "daily" }, "as": "daily" } }] } }];
+        assert!(matches!(check(&policy(&[], false), &nested), Err(ApiError::Forbidden(_))));
+        assert!(check(&policy(&[], true), &nested).is_ok());
+
+        let union = doc! { "$unionWith": { "db": "reports", "coll": "daily" } };
+        assert!(matches!(check(&policy(&[], false), &[union]), Err(ApiError::Forbidden(_))));
+
+        let unnested = doc! { "$unionWith": { "coll": "archive", "pipeline": [{ "$lookup": { "from": "users", "as": "users" } }] } };
+        assert!(check(&policy(&[], false), &[unnested]).is_ok());
+    }
+}
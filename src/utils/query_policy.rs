@@ -0,0 +1,403 @@
+use std::{collections::{HashMap, HashSet}, env, fs, sync::Arc};
+
+use mongodb::{bson::{Bson, Document}, options::{AggregateOptions, DeleteOptions, FindOneAndDeleteOptions, FindOneAndReplaceOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReplaceOptions, UpdateOptions}};
+use serde::Deserialize;
+
+use crate::{types::{errors::ApiError, mongo::{operation::Operation, requests::bulk_write::WriteOp}}, utils::pipeline::PipelinePolicy};
+
+// Run arbitrary JavaScript on the server
+const DEFAULT_DENIED_OPERATORS: [&str; 3] = ["$where", "$function", "$accumulator"];
+const DEFAULT_MAX_DEPTH: usize = 32;
+const DEFAULT_MAX_IN_LENGTH: usize = 1000;
+
+/// Overrides of the default rules, as written in `QUERY_POLICY_FILE`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RulesConfig {
+    denied_operators: Option<Vec<String>>,
+    max_depth: Option<usize>,
+    max_in_length: Option<usize>,
+    allow_unanchored_regex: Option<bool>,
+    write_targets: Option<Vec<String>>,
+    cross_db_lookup: Option<bool>
+}
+
+/// `{"defaults": {...}, "collections": {"db.coll": {...}, "db.*": {...}}}`, where collection entries override
+/// the defaults field by field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct PolicyConfig {
+    #[serde(default)]
+    defaults: RulesConfig,
+    #[serde(default)]
+    collections: HashMap<String, RulesConfig>
+}
+
+/// What filters and pipelines sent to one namespace may contain, and where its pipelines may write or read.
+#[derive(Debug)]
+pub struct QueryRules {
+    denied_operators: HashSet<String>,
+    max_depth: usize,
+    max_in_length: usize,
+    allow_unanchored_regex: bool,
+    pipeline: PipelinePolicy
+}
+
+impl QueryRules {
+    fn with_overrides(&self, config: RulesConfig) -> Self {
+        QueryRules {
+            denied_operators: config.denied_operators.map(HashSet::from_iter).unwrap_or_else(|| self.denied_operators.clone()),
+            max_depth: config.max_depth.unwrap_or(self.max_depth),
+            max_in_length: config.max_in_length.unwrap_or(self.max_in_length),
+            allow_unanchored_regex: config.allow_unanchored_regex.unwrap_or(self.allow_unanchored_regex),
+            pipeline: self.pipeline.with_overrides(config.write_targets, config.cross_db_lookup)
+        }
+    }
+
+    pub fn pipeline_policy(&self) -> &PipelinePolicy {
+        &self.pipeline
+    }
+
+    pub fn check_filter(&self, filter: &Document) -> Result<(), ApiError> {
+        self.check_doc("filter", filter, 1)
+    }
+
+    pub fn check_pipeline(&self, pipeline: &[Document]) -> Result<(), ApiError> {
+        pipeline
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, stage)| self.check_doc(&format!("pipeline.{}", i), stage, 1))
+    }
+
+    /// Checks every query expression `target` holds besides, or along with, a filter.
+    pub fn check<T: QueryExpressions>(&self, target: &T) -> Result<(), ApiError> {
+        target
+            .expressions()
+            .into_iter()
+            .try_for_each(|(path, doc)| self.check_doc(&path, doc, 1))
+    }
+
+    fn check_doc(&self, path: &str, doc: &Document, depth: usize) -> Result<(), ApiError> {
+        if depth > self.max_depth {
+            return Err(ApiError::query_policy(path, format!("Nesting is deeper than {} levels", self.max_depth)));
+        }
+
+        for (key, value) in doc {
+            let path = format!("{}.{}", path, key);
+
+            if self.denied_operators.contains(key) {
+                return Err(ApiError::query_policy(path, format!("Operator {} is not allowed", key)));
+            }
+
+            match (key.as_str(), value) {
+                ("$in" | "$nin", Bson::Array(values)) if values.len() > self.max_in_length => {
+                    return Err(ApiError::query_policy(path, format!("{} lists more than {} values", key, self.max_in_length)));
+                },
+                ("$regex", Bson::String(pattern)) => self.check_regex(&path, pattern)?,
+                _ => {}
+            }
+
+            self.check_value(&path, value, depth)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_value(&self, path: &str, value: &Bson, depth: usize) -> Result<(), ApiError> {
+        match value {
+            Bson::Document(doc) => self.check_doc(path, doc, depth + 1),
+            Bson::Array(_) if depth + 1 > self.max_depth => {
+                Err(ApiError::query_policy(path, format!("Nesting is deeper than {} levels", self.max_depth)))
+            },
+            Bson::Array(values) => values
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, value)| self.check_value(&format!("{}.{}", path, i), value, depth + 1)),
+            Bson::RegularExpression(regex) => self.check_regex(path, &regex.pattern),
+            _ => Ok(())
+        }
+    }
+
+    // Unanchored patterns cannot use an index and scan every document
+    fn check_regex(&self, path: &str, pattern: &str) -> Result<(), ApiError> {
+        if !self.allow_unanchored_regex && !pattern.starts_with('^') && !pattern.starts_with("\\A") {
+            return Err(ApiError::query_policy(path, "Regular expressions must be anchored with ^"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Something the server evaluates query or aggregation expressions of, such as `let` variables, a projection,
+/// `arrayFilters` or a `partialFilterExpression`, which reach the same operators as a filter does.
+pub trait QueryExpressions {
+    /// Each expression along with the request field it was read from.
+    fn expressions(&self) -> Vec<(String, &Document)>;
+}
+
+fn field<'a>(name: &str, doc: &'a Option<Document>) -> Vec<(String, &'a Document)> {
+    doc.iter().map(|doc| (name.to_string(), doc)).collect()
+}
+
+fn fields<'a>(name: &str, docs: &'a Option<Vec<Document>>) -> Vec<(String, &'a Document)> {
+    docs.iter().flatten().enumerate().map(|(i, doc)| (format!("{}.{}", name, i), doc)).collect()
+}
+
+impl QueryExpressions for FindOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [field("projection", &self.projection), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for FindOneOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [field("projection", &self.projection), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for FindOneAndUpdateOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [field("projection", &self.projection), fields("arrayFilters", &self.array_filters), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for FindOneAndReplaceOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [field("projection", &self.projection), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for FindOneAndDeleteOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [field("projection", &self.projection), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for UpdateOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        [fields("arrayFilters", &self.array_filters), field("let", &self.let_vars)].concat()
+    }
+}
+
+impl QueryExpressions for ReplaceOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        field("let", &self.let_vars)
+    }
+}
+
+impl QueryExpressions for DeleteOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        field("let", &self.let_vars)
+    }
+}
+
+impl QueryExpressions for AggregateOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        field("let", &self.let_vars)
+    }
+}
+
+impl QueryExpressions for IndexOptions {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        field("partialFilterExpression", &self.partial_filter_expression)
+    }
+}
+
+impl QueryExpressions for Operation {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        let filter = self.filter().map(|filter| ("filter".to_string(), filter));
+
+        let options = match self {
+            Operation::Find(_, opts) => opts.expressions(),
+            Operation::FindOne(_, opts) => opts.expressions(),
+            Operation::FindOneAndUpdate(_, _, opts) => opts.expressions(),
+            Operation::FindOneAndReplace(_, _, opts) => opts.expressions(),
+            Operation::FindOneAndDelete(_, opts) => opts.expressions(),
+            Operation::UpdateOne(_, _, opts) | Operation::UpdateMany(_, _, opts) => opts.expressions(),
+            Operation::ReplaceOne(_, _, opts) => opts.expressions(),
+            Operation::DeleteOne(_, opts) | Operation::DeleteMany(_, opts) => opts.expressions(),
+            Operation::Aggregate(pipeline, opts) => {
+                let stages = pipeline.iter().enumerate().map(|(i, stage)| (format!("pipeline.{}", i), stage));
+                stages.chain(opts.expressions()).collect()
+            },
+            Operation::InsertOne(..) | Operation::InsertMany(..) | Operation::CountDocuments(..) | Operation::Distinct(..) => Vec::new()
+        };
+
+        filter.into_iter().chain(options).collect()
+    }
+}
+
+impl QueryExpressions for WriteOp {
+    fn expressions(&self) -> Vec<(String, &Document)> {
+        let filter = self.filter().map(|filter| ("filter".to_string(), filter));
+
+        let options = match self {
+            WriteOp::UpdateOne(_, _, opts) | WriteOp::UpdateMany(_, _, opts) => opts.expressions(),
+            WriteOp::ReplaceOne(_, _, opts) => opts.expressions(),
+            WriteOp::DeleteOne(_, opts) | WriteOp::DeleteMany(_, opts) => opts.expressions(),
+            WriteOp::InsertOne(..) => Vec::new()
+        };
+
+        filter.into_iter().chain(options).collect()
+    }
+}
+
+impl Default for QueryRules {
+    fn default() -> Self {
+        QueryRules {
+            denied_operators: DEFAULT_DENIED_OPERATORS.iter().map(|op| op.to_string()).collect(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_in_length: DEFAULT_MAX_IN_LENGTH,
+            allow_unanchored_regex: false,
+            pipeline: PipelinePolicy::default()
+        }
+    }
+}
+
+/// Rules applied to filters and pipelines before they reach the server, read from `QUERY_POLICY_FILE`.
+/// Without it JavaScript operators and unanchored regular expressions are rejected everywhere.
+#[derive(Debug, Clone)]
+pub struct QueryPolicy {
+    defaults: Arc<QueryRules>,
+    collections: Arc<HashMap<String, Arc<QueryRules>>>
+}
+
+impl QueryPolicy {
+    pub fn from_env() -> Self {
+        let config: PolicyConfig = match env::var("QUERY_POLICY_FILE") {
+            Ok(path) => {
+                let contents = fs::read_to_string(&path).expect("Error: Failed to read QUERY_POLICY_FILE");
+                serde_json::from_str(&contents).expect("Error: Failed to parse QUERY_POLICY_FILE")
+            },
+            Err(_) => PolicyConfig::default()
+        };
+
+        let base = QueryRules { pipeline: PipelinePolicy::from_env(), ..QueryRules::default() };
+        let defaults = Arc::new(base.with_overrides(config.defaults));
+        let collections = config.collections
+            .into_iter()
+            .map(|(namespace, overrides)| (namespace, Arc::new(defaults.with_overrides(overrides))))
+            .collect();
+
+        QueryPolicy { defaults, collections: Arc::new(collections) }
+    }
+
+    /// Rules of `db.coll`, falling back to those of `db.*` and then to the defaults. Requests spanning a whole
+    /// database, such as database change streams, pass no collection.
+    pub fn rules(&self, db: &str, coll: Option<&str>) -> Arc<QueryRules> {
+        let exact = coll.and_then(|coll| self.collections.get(&format!("{}.{}", db, coll)));
+
+        exact
+            .or_else(|| self.collections.get(&format!("{}.*", db)))
+            .unwrap_or(&self.defaults)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::{bson::{doc, Regex}, options::{FindOptions, IndexOptions, UpdateOptions}};
+
+    use super::*;
+
+    // Field path of the violation, or `None` when the check passed
+    fn violation(result: Result<(), ApiError>) -> Option<String> {
+        match result {
+            Ok(()) => None,
+            Err(ApiError::QueryPolicy { field, .. }) => Some(field),
+            Err(other) => panic!("unexpected error {:?}", other)
+        }
+    }
+
+    fn rules(config: RulesConfig) -> QueryRules {
+        QueryRules::default().with_overrides(config)
+    }
+
+    #[test]
+    fn rejects_denied_operators_in_filters() {
+        let rules = QueryRules::default();
+
+        assert_eq!(violation(rules.check_filter(&doc! { "$where": "true" })), Some("filter.$where".to_string()));
+        assert_eq!(violation(rules.check_filter(&doc! { "$and": [{ "$where": "true" }] })), Some("filter.$and.0.$where".to_string()));
+        assert_eq!(violation(rules.check_filter(&doc! { "a": 1 })), None);
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let rules = rules(RulesConfig { max_depth: Some(3), ..Default::default() });
+
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "b": { "c": 1 } } })), None);
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "b": { "c": { "d": 1 } } } })), Some("filter.a.b.c".to_string()));
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "b": [[1]] } })), Some("filter.a.b.0".to_string()));
+    }
+
+    #[test]
+    fn limits_in_lists() {
+        let rules = rules(RulesConfig { max_in_length: Some(2), ..Default::default() });
+
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$in": [1, 2] } })), None);
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$in": [1, 2, 3] } })), Some("filter.a.$in".to_string()));
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$nin": [1, 2, 3] } })), Some("filter.a.$nin".to_string()));
+    }
+
+    #[test]
+    fn requires_anchored_regular_expressions() {
+        let rules = QueryRules::default();
+        let unanchored = Regex { pattern: "abc".to_string(), options: String::new() };
+
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$regex": "^abc" } })), None);
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$regex": "\\Aabc" } })), None);
+        assert_eq!(violation(rules.check_filter(&doc! { "a": { "$regex": "abc" } })), Some("filter.a.$regex".to_string()));
+        assert_eq!(violation(rules.check_filter(&doc! { "a": unanchored.clone() })), Some("filter.a".to_string()));
+
+        let permissive = self::rules(RulesConfig { allow_unanchored_regex: Some(true), ..Default::default() });
+        assert_eq!(violation(permissive.check_filter(&doc! { "a": unanchored })), None);
+    }
+
+    #[test]
+    fn checks_pipelines() {
+        let rules = QueryRules::default();
+        let pipeline = [doc! { "$match": { "a": 1 } }, doc! { "$group": { "_id": null, "x": { "$accumulator": {} } } }];
+
+        assert_eq!(violation(rules.check_pipeline(&pipeline)), Some("pipeline.1.$group.x.$accumulator".to_string()));
+    }
+
+    #[test]
+    fn checks_let_and_projection() {
+        let rules = QueryRules::default();
+
+        let opts = FindOptions::builder().let_vars(doc! { "x": { "$function": {} } }).build();
+        assert_eq!(violation(rules.check(&opts)), Some("let.x.$function".to_string()));
+
+        let opts = FindOptions::builder().projection(doc! { "x": { "$function": {} } }).build();
+        assert_eq!(violation(rules.check(&opts)), Some("projection.x.$function".to_string()));
+
+        let opts = FindOptions::builder().projection(doc! { "x": 1 }).let_vars(doc! { "y": 1 }).build();
+        assert_eq!(violation(rules.check(&opts)), None);
+    }
+
+    #[test]
+    fn checks_array_filters_and_partial_filter_expressions() {
+        let rules = QueryRules::default();
+
+        let opts = UpdateOptions::builder().array_filters(vec![doc! { "e.a": 1 }, doc! { "$where": "true" }]).build();
+        assert_eq!(violation(rules.check(&opts)), Some("arrayFilters.1.$where".to_string()));
+
+        let opts = IndexOptions::builder().partial_filter_expression(doc! { "$where": "true" }).build();
+        assert_eq!(violation(rules.check(&opts)), Some("partialFilterExpression.$where".to_string()));
+    }
+
+    #[test]
+    fn checks_operations() {
+        let rules = QueryRules::default();
+
+        let op = Operation::Aggregate(vec![doc! { "$match": { "a": 1 } }], AggregateOptions::builder().let_vars(doc! { "x": { "$function": {} } }).build());
+        assert_eq!(violation(rules.check(&op)), Some("let.x.$function".to_string()));
+
+        let op = Operation::DeleteOne(doc! { "$where": "true" }, DeleteOptions::default());
+        assert_eq!(violation(rules.check(&op)), Some("filter.$where".to_string()));
+
+        let op = WriteOp::UpdateOne(doc! { "a": 1 }, doc! { "$set": { "e.$[x]": 1 } }, UpdateOptions::builder().array_filters(vec![doc! { "$where": "true" }]).build());
+        assert_eq!(violation(rules.check(&op)), Some("arrayFilters.0.$where".to_string()));
+    }
+}
@@ -0,0 +1,100 @@
+use futures::TryStreamExt;
+use mongodb::{Collection, ClientSession, bson::{Bson, Document}, error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT}, options::TransactionOptions};
+use serde_json::{Value, json};
+
+use crate::{middleware::headers::ExtJsonFormat, types::{errors::ApiError, mongo::operation::Operation}, utils::mongo::{bson_as_json, doc_as_json}};
+
+// Attempts per transaction before a transient error is returned to the caller
+const MAX_TRANSACTION_ATTEMPTS: usize = 3;
+
+/// Runs a single operation inside `session`, producing the same JSON as the matching route.
+pub async fn run_operation(coll: &Collection<Document>, op: Operation, session: &mut ClientSession, format: ExtJsonFormat) -> Result<Value, mongodb::error::Error> {
+    match op {
+        Operation::Find(filter, opts) => {
+            let mut cursor = coll.find_with_session(filter, opts, session).await?;
+            let docs: Vec<Document> = cursor.stream(session).try_collect().await?;
+            Ok(Value::Array(docs.into_iter().map(|doc| doc_as_json(doc, format)).collect()))
+        },
+        Operation::FindOne(filter, opts) => {
+            let doc = coll.find_one_with_session(filter, opts, session).await?;
+            Ok(doc_as_json(doc.unwrap_or_default(), format))
+        },
+        Operation::FindOneAndUpdate(query, update, opts) => {
+            let doc = coll.find_one_and_update_with_session(query, update, opts, session).await?;
+            Ok(doc_as_json(doc.unwrap_or_default(), format))
+        },
+        Operation::FindOneAndReplace(query, replacement, opts) => {
+            let doc = coll.find_one_and_replace_with_session(query, replacement, opts, session).await?;
+            Ok(doc_as_json(doc.unwrap_or_default(), format))
+        },
+        Operation::FindOneAndDelete(query, opts) => {
+            let doc = coll.find_one_and_delete_with_session(query, opts, session).await?;
+            Ok(doc_as_json(doc.unwrap_or_default(), format))
+        },
+        Operation::InsertOne(doc, opts) => Ok(json!(coll.insert_one_with_session(doc, opts, session).await?)),
+        Operation::InsertMany(docs, opts) => Ok(json!(coll.insert_many_with_session(docs, opts, session).await?)),
+        Operation::UpdateOne(query, update, opts) => Ok(json!(coll.update_one_with_session(query, update, opts, session).await?)),
+        Operation::UpdateMany(query, update, opts) => Ok(json!(coll.update_many_with_session(query, update, opts, session).await?)),
+        Operation::ReplaceOne(query, replacement, opts) => Ok(json!(coll.replace_one_with_session(query, replacement, opts, session).await?)),
+        Operation::DeleteOne(query, opts) => Ok(json!(coll.delete_one_with_session(query, opts, session).await?)),
+        Operation::DeleteMany(query, opts) => Ok(json!(coll.delete_many_with_session(query, opts, session).await?)),
+        Operation::Aggregate(pipeline, opts) => {
+            let mut cursor = coll.aggregate_with_session(pipeline, opts, session).await?;
+            let docs: Vec<Document> = cursor.stream(session).try_collect().await?;
+            Ok(Value::Array(docs.into_iter().map(|doc| doc_as_json(doc, format)).collect()))
+        },
+        Operation::CountDocuments(filter, opts) => Ok(json!({ "count": coll.count_documents_with_session(filter, opts, session).await? })),
+        Operation::Distinct(key, filter, opts) => {
+            let values = coll.distinct_with_session(key, filter, opts, session).await?;
+            Ok(json!({ "values": bson_as_json(Bson::Array(values), format) }))
+        }
+    }
+}
+
+/// Commits the session's transaction, retrying while the outcome is `UnknownTransactionCommitResult`.
+pub async fn commit_transaction(session: &mut ClientSession) -> Result<(), mongodb::error::Error> {
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        match session.commit_transaction().await {
+            Err(err) if err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && attempt < MAX_TRANSACTION_ATTEMPTS => continue,
+            result => return result
+        }
+    }
+}
+
+/// Runs every operation in one transaction, committing only if all succeed. The whole transaction is
+/// retried on `TransientTransactionError` and the commit on `UnknownTransactionCommitResult`.
+pub async fn run_transaction(session: &mut ClientSession, ops: &[(Collection<Document>, Operation)], opts: TransactionOptions, format: ExtJsonFormat) -> Result<Vec<Value>, ApiError> {
+    let mut attempt = 0;
+
+    'transaction: loop {
+        attempt += 1;
+        session.start_transaction(opts.clone()).await?;
+
+        let mut results = Vec::with_capacity(ops.len());
+        for (coll, op) in ops {
+            match run_operation(coll, op.clone(), session, format).await {
+                Ok(result) => results.push(result),
+                Err(err) => {
+                    // The server may already have aborted, the original error is the one worth reporting
+                    let _ = session.abort_transaction().await;
+
+                    if err.contains_label(TRANSIENT_TRANSACTION_ERROR) && attempt < MAX_TRANSACTION_ATTEMPTS {
+                        continue 'transaction;
+                    }
+
+                    return Err(err.into());
+                }
+            }
+        }
+
+        match commit_transaction(session).await {
+            Ok(()) => return Ok(results),
+            Err(err) if err.contains_label(TRANSIENT_TRANSACTION_ERROR) && attempt < MAX_TRANSACTION_ATTEMPTS => continue 'transaction,
+            Err(err) => return Err(err.into())
+        }
+    }
+}
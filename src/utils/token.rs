@@ -0,0 +1,18 @@
+use std::fmt::Write;
+
+use rand::RngCore;
+
+/// Random 256-bit identifier, hex encoded, used for session tokens and cursor ids.
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    hex(&bytes)
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut out, byte| {
+        let _ = write!(out, "{:02x}", byte);
+        out
+    })
+}